@@ -0,0 +1,66 @@
+//! Benchmarks comparing unstyled rendering with `--color never`.
+//!
+//! Run with `cargo bench`.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use tempfile::NamedTempFile;
+
+/// Number of examples in the generated page.
+const EXAMPLE_COUNT: usize = 5000;
+
+/// Create a large page, so that rendering dominates the process startup time.
+fn create_page() -> NamedTempFile {
+    let mut file = NamedTempFile::new().unwrap();
+    writeln!(
+        file,
+        "# inkscape\n\n> An SVG (Scalable Vector Graphics) editing program.\n"
+    )
+    .unwrap();
+    for i in 0..EXAMPLE_COUNT {
+        writeln!(
+            file,
+            "- Export an SVG file into a bitmap ({}):\n\n`inkscape {{{{filename.svg}}}} --export-png={{{{filename.png}}}} --export-dpi={{{{{}}}}}`\n",
+            i, i
+        )
+        .unwrap();
+    }
+    file.flush().unwrap();
+    file
+}
+
+fn bench_render(c: &mut Criterion) {
+    let run = escargot::CargoBuild::new()
+        .bin("tldr")
+        .current_release()
+        .current_target()
+        .run()
+        .unwrap();
+    let page = create_page();
+    let page_path = page.path().to_str().unwrap();
+
+    let mut group = c.benchmark_group("render");
+    let variants: &[(&str, &[&str])] = &[
+        ("color never", &["--color", "never"]),
+        ("no styling", &["--no-styling"]),
+    ];
+    for (name, args) in variants {
+        group.bench_function(*name, |b| {
+            b.iter(|| {
+                let status = Command::new(run.path())
+                    .args(*args)
+                    .args(&["-f", page_path])
+                    .stdout(Stdio::null())
+                    .status()
+                    .unwrap();
+                assert!(status.success());
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_render);
+criterion_main!(benches);
@@ -0,0 +1,114 @@
+//! A rendering micro-benchmark, used with `--benchmark`.
+//!
+//! The benchmark renders a fixed corpus of pages (the test fixtures and the
+//! theme preview page) without any network or cache access, so that its
+//! results can be compared across versions.
+
+use std::fmt;
+use std::io;
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::error::TealdeerError;
+use crate::formatter::{print_lines, THEME_PREVIEW_PAGE};
+use crate::tokenizer::Tokenizer;
+use crate::types::OsType;
+
+/// The pages that are rendered, with the platform they are rendered for.
+const CORPUS: [(&str, Option<OsType>); 4] = [
+    (include_str!("../tests/inkscape-v1.md"), None),
+    (include_str!("../tests/inkscape-v2.md"), None),
+    (include_str!("../tests/tar-ar.md"), None),
+    (THEME_PREVIEW_PAGE, Some(OsType::Linux)),
+];
+
+/// The results of a benchmark run.
+#[derive(Debug)]
+pub struct Report {
+    /// The total time it took to render all pages
+    pub total: Duration,
+    /// The rendering time of each page, sorted
+    pub latencies: Vec<Duration>,
+}
+
+impl Report {
+    /// Return the latency that `percent` percent of the pages were rendered
+    /// in (nearest-rank method).
+    pub fn percentile(&self, percent: usize) -> Duration {
+        if self.latencies.is_empty() {
+            return Duration::default();
+        }
+        let rank = (percent * self.latencies.len() + 99) / 100;
+        self.latencies[rank.clamp(1, self.latencies.len()) - 1]
+    }
+
+    /// Return the number of pages rendered per second.
+    pub fn pages_per_second(&self) -> f64 {
+        #[allow(clippy::cast_precision_loss)]
+        let pages = self.latencies.len() as f64;
+        pages / self.total.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Rendered {} pages in {:.3} s ({:.0} pages/s)",
+            self.latencies.len(),
+            self.total.as_secs_f64(),
+            self.pages_per_second()
+        )?;
+        write!(
+            f,
+            "Latency per page: p50 {} us, p90 {} us, p99 {} us, max {} us",
+            self.percentile(50).as_micros(),
+            self.percentile(90).as_micros(),
+            self.percentile(99).as_micros(),
+            self.percentile(100).as_micros()
+        )
+    }
+}
+
+/// Render the corpus `runs` times with the styles of `config`, discarding the
+/// output.
+pub fn run(runs: usize, config: &Config) -> Result<Report, TealdeerError> {
+    let mut latencies = Vec::with_capacity(runs * CORPUS.len());
+    let start = Instant::now();
+    for _ in 0..runs {
+        for (page, platform) in &CORPUS {
+            let page_start = Instant::now();
+            let mut tokenizer = Tokenizer::new(page.as_bytes());
+            print_lines(&mut io::sink(), &mut tokenizer, *platform, Some(80), config)?;
+            latencies.push(page_start.elapsed());
+        }
+    }
+    let total = start.elapsed();
+    latencies.sort();
+    Ok(Report { total, latencies })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Report;
+    use std::time::Duration;
+
+    #[test]
+    fn test_percentile() {
+        let report = Report {
+            total: Duration::from_millis(10),
+            latencies: (1..=10).map(Duration::from_millis).collect(),
+        };
+        assert_eq!(report.percentile(50), Duration::from_millis(5));
+        assert_eq!(report.percentile(90), Duration::from_millis(9));
+        assert_eq!(report.percentile(99), Duration::from_millis(10));
+        assert_eq!(report.percentile(0), Duration::from_millis(1));
+        assert!((report.pages_per_second() - 1000.0).abs() < 1e-6);
+
+        let empty = Report {
+            total: Duration::default(),
+            latencies: Vec::new(),
+        };
+        assert_eq!(empty.percentile(50), Duration::default());
+    }
+}
@@ -1,20 +1,27 @@
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 use std::fs;
 use std::io::{Error as IoError, Read, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 use ansi_term::{Color, Style};
-use app_dirs::{get_app_root, AppDataType};
 use log::debug;
 use serde_derive::{Deserialize, Serialize};
 
 use crate::error::TealdeerError::{self, ConfigError};
-use crate::types::PathSource;
+use crate::paths::{self, DirKind};
+use crate::types::{OsType, PathSource};
 
 pub const CONFIG_FILE_NAME: &str = "config.toml";
 pub const MAX_CACHE_AGE: Duration = Duration::from_secs(2_592_000); // 30 days
 const DEFAULT_UPDATE_INTERVAL_HOURS: u64 = MAX_CACHE_AGE.as_secs() / 3600; // 30 days
+const DEFAULT_PAGE_URL_BASE: &str = "https://tldr.inbrowser.app/pages";
+const DEFAULT_NO_MATCH_MESSAGE: &str = "No examples matched \"{pattern}\"";
+pub const DEFAULT_USER_AGENT: &str = concat!("tealdeer/", env!("CARGO_PKG_VERSION"));
+const DEFAULT_ARCHIVE_URL: &str = "https://github.com/tldr-pages/tldr/archive/master.tar.gz";
+const DEFAULT_CODE_INDENT: usize = 6;
+const DEFAULT_DESCRIPTION_INDENT: usize = 2;
 
 fn default_underline() -> bool {
     false
@@ -56,27 +63,99 @@ impl From<RawColor> for Color {
     }
 }
 
+/// A text attribute, in the `attributes` list of a style.
 #[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum RawAttribute {
+    Bold,
+    Dim,
+    Italic,
+    Underline,
+    Blink,
+    Reverse,
+    Strikethrough,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 struct RawStyle {
     pub foreground: Option<RawColor>,
+    /// Replaces `foreground` in terminals with 24-bit colors
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub foreground_truecolor: Option<RawColor>,
+    /// Replaces `foreground` in terminals with 256 colors (and with 24-bit
+    /// colors, if `foreground_truecolor` is not set)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub foreground_256: Option<RawColor>,
     pub background: Option<RawColor>,
     #[serde(default = "default_underline")]
     pub underline: bool,
     #[serde(default = "default_bold")]
     pub bold: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attributes: Option<Vec<RawAttribute>>,
 }
 
 impl Default for RawStyle {
     fn default() -> Self {
         Self {
             foreground: None,
+            foreground_truecolor: None,
+            foreground_256: None,
             background: None,
             underline: false,
             bold: false,
+            attributes: None,
         }
     }
 } // impl RawStyle
 
+impl RawStyle {
+    /// Return the style with the foreground color variant for `depth`.
+    fn for_color_depth(self, depth: ColorDepth) -> Self {
+        let variant = match depth {
+            ColorDepth::TrueColor => self.foreground_truecolor.or(self.foreground_256),
+            ColorDepth::Ansi256 => self.foreground_256,
+            ColorDepth::Basic => None,
+        };
+        Self {
+            foreground: variant.or(self.foreground),
+            foreground_truecolor: None,
+            foreground_256: None,
+            ..self
+        }
+    }
+}
+
+/// The colors that the terminal supports.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum ColorDepth {
+    /// The 16 ANSI colors
+    Basic,
+    /// 256 colors
+    Ansi256,
+    /// 24-bit RGB colors
+    TrueColor,
+}
+
+impl ColorDepth {
+    /// Detect the color depth from the `COLORTERM` and `TERM` environment
+    /// variables.
+    fn detect() -> Self {
+        Self::from_env(
+            env::var("COLORTERM").ok().as_deref(),
+            env::var("TERM").ok().as_deref(),
+        )
+    }
+
+    fn from_env(colorterm: Option<&str>, term: Option<&str>) -> Self {
+        match (colorterm, term) {
+            (Some("truecolor"), _) | (Some("24bit"), _) => Self::TrueColor,
+            (_, Some(term)) if term.contains("256color") => Self::Ansi256,
+            _ => Self::Basic,
+        }
+    }
+}
+
 impl From<RawStyle> for Style {
     fn from(raw_style: RawStyle) -> Self {
         let mut style = Self::default();
@@ -97,30 +176,269 @@ impl From<RawStyle> for Style {
             style = style.bold();
         }
 
+        for attribute in raw_style.attributes.unwrap_or_default() {
+            style = match attribute {
+                RawAttribute::Bold => style.bold(),
+                RawAttribute::Dim => style.dimmed(),
+                RawAttribute::Italic => style.italic(),
+                RawAttribute::Underline => style.underline(),
+                RawAttribute::Blink => style.blink(),
+                RawAttribute::Reverse => style.reverse(),
+                RawAttribute::Strikethrough => style.strikethrough(),
+            };
+        }
+
         style
     }
 }
 
-#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+/// Style entries that are not set fall back to the theme, or to no styling
+/// at all if no theme is selected.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
 struct RawStyleConfig {
-    #[serde(default)]
-    pub description: RawStyle,
-    #[serde(default)]
-    pub command_name: RawStyle,
-    #[serde(default)]
-    pub example_text: RawStyle,
-    #[serde(default)]
-    pub example_code: RawStyle,
-    #[serde(default)]
-    pub example_variable: RawStyle,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<RawStyle>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command_name: Option<RawStyle>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub example_text: Option<RawStyle>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub example_code: Option<RawStyle>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub example_variable: Option<RawStyle>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link: Option<RawStyle>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line_number: Option<RawStyle>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub synopsis: Option<RawStyle>,
+}
+
+impl RawStyleConfig {
+    /// Fill in the entries that are not set with the entries of `base`.
+    fn or(self, base: Self) -> Self {
+        Self {
+            description: self.description.or(base.description),
+            command_name: self.command_name.or(base.command_name),
+            example_text: self.example_text.or(base.example_text),
+            example_code: self.example_code.or(base.example_code),
+            example_variable: self.example_variable.or(base.example_variable),
+            link: self.link.or(base.link),
+            line_number: self.line_number.or(base.line_number),
+            synopsis: self.synopsis.or(base.synopsis),
+        }
+    }
+
+    /// Return the style entries with the foreground color variants for `depth`.
+    fn for_color_depth(self, depth: ColorDepth) -> Self {
+        let select = |style: Option<RawStyle>| style.map(|style| style.for_color_depth(depth));
+        Self {
+            description: select(self.description),
+            command_name: select(self.command_name),
+            example_text: select(self.example_text),
+            example_code: select(self.example_code),
+            example_variable: select(self.example_variable),
+            link: select(self.link),
+            line_number: select(self.line_number),
+            synopsis: select(self.synopsis),
+        }
+    }
+}
+
+/// A built-in set of styles, selected with `--theme` or `[display] theme`.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Theme {
+    Default,
+    Ocean,
+    Monochrome,
+    HighContrast,
+}
+
+impl Theme {
+    /// Return the style entries of this theme.
+    fn style_config(self) -> RawStyleConfig {
+        let style = |foreground: Option<RawColor>, underline: bool, bold: bool| {
+            Some(RawStyle {
+                foreground,
+                underline,
+                bold,
+                ..RawStyle::default()
+            })
+        };
+        let dimmed = || {
+            Some(RawStyle {
+                attributes: Some(vec![RawAttribute::Dim]),
+                ..RawStyle::default()
+            })
+        };
+        match self {
+            Self::Default => RawStyleConfig {
+                description: style(None, false, false),
+                command_name: style(Some(RawColor::Cyan), false, false),
+                example_text: style(Some(RawColor::Green), false, false),
+                example_code: style(Some(RawColor::Cyan), false, false),
+                example_variable: style(Some(RawColor::Cyan), true, false),
+                link: style(Some(RawColor::Blue), true, false),
+                line_number: dimmed(),
+                synopsis: style(None, false, true),
+            },
+            Self::Ocean => RawStyleConfig {
+                description: style(Some(RawColor::Cyan), false, false),
+                command_name: style(Some(RawColor::Blue), false, true),
+                example_text: style(Some(RawColor::Cyan), false, false),
+                example_code: style(Some(RawColor::Blue), false, false),
+                example_variable: style(Some(RawColor::Ansi(39)), true, false),
+                link: style(Some(RawColor::Ansi(39)), true, false),
+                line_number: dimmed(),
+                synopsis: style(Some(RawColor::Cyan), false, true),
+            },
+            // Attributes only, no colors
+            Self::Monochrome => RawStyleConfig {
+                description: style(None, false, false),
+                command_name: style(None, false, true),
+                example_text: style(None, false, true),
+                example_code: style(None, false, false),
+                example_variable: style(None, true, false),
+                link: style(None, true, false),
+                line_number: dimmed(),
+                synopsis: style(None, false, true),
+            },
+            // Bright white and bright yellow
+            Self::HighContrast => RawStyleConfig {
+                description: style(Some(RawColor::Ansi(15)), false, false),
+                command_name: style(Some(RawColor::Ansi(11)), false, true),
+                example_text: style(Some(RawColor::Ansi(15)), false, true),
+                example_code: style(Some(RawColor::Ansi(11)), false, false),
+                example_variable: style(Some(RawColor::Ansi(11)), true, true),
+                link: style(Some(RawColor::Ansi(14)), true, true),
+                line_number: style(Some(RawColor::Ansi(15)), false, false),
+                synopsis: style(Some(RawColor::Ansi(15)), false, true),
+            },
+        }
+    }
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)]
 struct RawDisplayConfig {
     #[serde(default)]
     pub compact: bool,
     #[serde(default)]
     pub use_pager: bool,
+    #[serde(default)]
+    pub command_prefix: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub theme: Option<Theme>,
+    #[serde(default)]
+    pub example_order: ExampleOrder,
+    #[serde(default)]
+    pub emphasize_first_example: bool,
+    #[serde(default)]
+    pub wrap_code: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_width: Option<usize>,
+    #[serde(default)]
+    pub layout: Layout,
+    #[serde(default)]
+    pub title_case: TitleCase,
+    #[serde(default)]
+    pub line_numbering: LineNumbering,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code_indent: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description_indent: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_url_base: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_not_found_command: Option<String>,
+    #[serde(default)]
+    pub show_resolution_caption: bool,
+    #[serde(default)]
+    pub hyperlink_more_info: bool,
+    #[serde(default)]
+    pub hyperlink_see_also: bool,
+    #[serde(default)]
+    pub expand_env: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trim_example_whitespace: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_separator: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_match_message: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub footer: Option<String>,
+    #[serde(default)]
+    pub strict_config: bool,
+}
+
+/// The order in which the examples of a page are rendered.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExampleOrder {
+    /// The order of the page source
+    Source,
+    /// Sorted by the example description
+    Alphabetical,
+}
+
+impl Default for ExampleOrder {
+    fn default() -> Self {
+        Self::Source
+    }
+}
+
+/// The layout of the examples of a page.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    /// The example description above the example code
+    Stacked,
+    /// The example description and the example code on the same line, in two
+    /// columns (if they fit into the terminal)
+    Inline,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self::Stacked
+    }
+}
+
+/// How the title of a page (the command name) is cased.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TitleCase {
+    /// The title of the page source
+    Source,
+    /// The title in lowercase
+    Lower,
+    /// The command name that was requested
+    Command,
+}
+
+impl Default for TitleCase {
+    fn default() -> Self {
+        Self::Source
+    }
+}
+
+/// How the example code lines are numbered (with `--line-numbers`).
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LineNumbering {
+    /// Consecutive numbers for all example code lines of the page
+    Page,
+    /// The numbers start at 1 for every example
+    Example,
+}
+
+impl Default for LineNumbering {
+    fn default() -> Self {
+        Self::Page
+    }
 }
 
 /// Serde doesn't support default values yet (tracking issue:
@@ -137,6 +455,22 @@ struct RawUpdatesConfig {
     pub auto_update: bool,
     #[serde(default = "default_auto_update_interval_hours")]
     pub auto_update_interval_hours: u64,
+    #[serde(default)]
+    pub prompt_on_stale: bool,
+    #[serde(default)]
+    pub stale_exit_code: u8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub networking: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_cache_age_hours: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delta_url: Option<String>,
 }
 
 impl Default for RawUpdatesConfig {
@@ -144,6 +478,14 @@ impl Default for RawUpdatesConfig {
         Self {
             auto_update: false,
             auto_update_interval_hours: DEFAULT_UPDATE_INTERVAL_HOURS,
+            prompt_on_stale: false,
+            stale_exit_code: 0,
+            archive_url: None,
+            user_agent: None,
+            headers: BTreeMap::new(),
+            networking: None,
+            max_cache_age_hours: None,
+            delta_url: None,
         }
     }
 }
@@ -152,18 +494,31 @@ impl Default for RawUpdatesConfig {
 struct RawDirectoriesConfig {
     #[serde(default)]
     pub custom_pages_dir: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub platform_order: Vec<String>,
+    #[serde(default)]
+    pub cache_readonly: bool,
 }
 
 impl Default for RawDirectoriesConfig {
     fn default() -> Self {
         Self {
-            custom_pages_dir: get_app_root(AppDataType::UserData, &crate::APP_INFO)
-                .map(|path| path.join("pages"))
-                .ok(),
+            custom_pages_dir: paths::default_dir(DirKind::Data).map(|path| path.join("pages")),
+            platform_order: Vec::new(),
+            cache_readonly: false,
         }
     }
 }
 
+/// Defaults for a single command, in a `[commands."<name>"]` section.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+struct RawCommandConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub platform: Option<OsType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+}
+
 #[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
 struct RawConfig {
     #[serde(default)]
@@ -174,19 +529,63 @@ struct RawConfig {
     updates: RawUpdatesConfig,
     #[serde(default)]
     directories: RawDirectoriesConfig,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    commands: BTreeMap<String, RawCommandConfig>,
 }
 
 impl RawConfig {
     fn new() -> Self {
-        let mut raw_config = Self::default();
-
         // Set default config
-        raw_config.style.example_text.foreground = Some(RawColor::Green);
-        raw_config.style.command_name.foreground = Some(RawColor::Cyan);
-        raw_config.style.example_code.foreground = Some(RawColor::Cyan);
-        raw_config.style.example_variable.foreground = Some(RawColor::Cyan);
-        raw_config.style.example_variable.underline = true;
+        Self {
+            style: Theme::Default.style_config(),
+            ..Self::default()
+        }
+    }
 
+    /// Return the default config with every optional key set, to list all
+    /// keys in the seed config. Keys without a default get an example value.
+    fn complete() -> Self {
+        let mut raw_config = Self::new();
+        for style in vec![
+            &mut raw_config.style.description,
+            &mut raw_config.style.command_name,
+            &mut raw_config.style.example_text,
+            &mut raw_config.style.example_code,
+            &mut raw_config.style.example_variable,
+            &mut raw_config.style.link,
+            &mut raw_config.style.line_number,
+            &mut raw_config.style.synopsis,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            style.attributes.get_or_insert_with(Vec::new);
+        }
+        raw_config.display.theme = Some(Theme::Default);
+        raw_config.display.code_indent = Some(DEFAULT_CODE_INDENT);
+        raw_config.display.description_indent = Some(DEFAULT_DESCRIPTION_INDENT);
+        raw_config.display.max_width = Some(0);
+        raw_config.display.trim_example_whitespace = Some(true);
+        raw_config.display.page_url_base = Some(DEFAULT_PAGE_URL_BASE.into());
+        raw_config.display.on_not_found_command =
+            Some("echo \"No page for $TEALDEER_COMMAND\"".into());
+        raw_config.display.page_separator = Some("== {name} ==".into());
+        raw_config.display.no_match_message = Some(DEFAULT_NO_MATCH_MESSAGE.into());
+        raw_config.display.header = Some(String::new());
+        raw_config.display.footer = Some(String::new());
+        raw_config.updates.archive_url = Some(DEFAULT_ARCHIVE_URL.into());
+        raw_config.updates.user_agent = Some(DEFAULT_USER_AGENT.into());
+        raw_config.directories.platform_order = vec!["linux".into(), "common".into()];
+        raw_config.updates.networking = Some(true);
+        raw_config.updates.max_cache_age_hours = Some(0);
+        raw_config.updates.delta_url = Some(String::new());
+        raw_config.commands.insert(
+            "git".into(),
+            RawCommandConfig {
+                platform: Some(OsType::Linux),
+                language: Some("en".into()),
+            },
+        );
         raw_config
     }
 }
@@ -198,23 +597,131 @@ pub struct StyleConfig {
     pub example_text: Style,
     pub example_code: Style,
     pub example_variable: Style,
+    pub link: Style,
+    pub line_number: Style,
+    pub synopsis: Style,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct DisplayConfig {
     pub compact: bool,
     pub use_pager: bool,
+    pub command_prefix: String,
+    pub example_order: ExampleOrder,
+    pub emphasize_first_example: bool,
+    pub wrap_code: bool,
+    /// The maximum width to render for, narrower terminals are still used
+    /// in full
+    pub max_width: Option<usize>,
+    pub layout: Layout,
+    pub title_case: TitleCase,
+    pub line_numbering: LineNumbering,
+    /// Number of spaces before example code
+    pub code_indent: usize,
+    /// Number of spaces before the page description and example descriptions
+    pub description_indent: usize,
+    pub page_url_base: String,
+    /// Shell command that is run when a page is not found
+    pub on_not_found_command: Option<String>,
+    /// Whether the language and platform of the page are printed below it
+    pub show_resolution_caption: bool,
+    /// Whether the URL of the "More information" line is a terminal hyperlink
+    /// (OSC 8)
+    pub hyperlink_more_info: bool,
+    /// Whether the commands of the "See also" line are terminal hyperlinks to
+    /// their pages (OSC 8)
+    pub hyperlink_see_also: bool,
+    /// Whether environment variables (`$VAR` and `${VAR}`) in example code
+    /// are replaced with their values
+    pub expand_env: bool,
+    /// Whether trailing whitespace is trimmed from the example code lines
+    pub trim_example_whitespace: bool,
+    /// Line above each page when rendering several pages, `{name}` expands to
+    /// the command (a rule with the name if `None`)
+    pub page_separator: Option<String>,
+    /// Message printed when no examples match `--grep`, `{pattern}` expands
+    /// to the pattern
+    pub no_match_message: String,
+    /// Line above each rendered page, `{name}` and `{platform}` expand to the
+    /// page name and platform
+    pub header: Option<String>,
+    /// Line below each rendered page, with the same expansions as `header`
+    pub footer: Option<String>,
+    /// Whether the header and footer are rendered dimmed (not without styles)
+    pub dim_header_footer: bool,
+    /// Whether *emphasis* in descriptions is rendered in bold (not without
+    /// styles)
+    pub bold_emphasis: bool,
+    /// Whether the description of pages is omitted (set through
+    /// `--examples-only`)
+    pub examples_only: bool,
+    /// Whether the example code lines are numbered (set through
+    /// `--line-numbers`)
+    pub line_numbers: bool,
+    /// Only the examples whose description contains this text (ignoring
+    /// case) are rendered (set through `--grep`)
+    pub example_filter: Option<String>,
+    /// Whether examples with the same code (ignoring the placeholders) are
+    /// collapsed into the first one (set through `--dedup`)
+    pub dedup_examples: bool,
+    /// The name of the requested command, with spaces between the words (set
+    /// when showing a command)
+    pub requested_command: Option<String>,
+    /// Values to substitute placeholders with, by placeholder (set through
+    /// `--set`)
+    pub placeholder_values: HashMap<String, String>,
+    /// The prefix of the numbered positional arguments that replace the
+    /// placeholders, `$` or `%` (set through `--placeholders positional`)
+    pub positional_prefix: Option<char>,
+    /// The web URLs of the pages of the commands of the "See also" line (set
+    /// when showing a command with `hyperlink_see_also`)
+    pub see_also_urls: HashMap<String, String>,
+    /// Whether a reset escape sequence ends the rendered page (set when the
+    /// styles are forced with `--color always`, but stdout is no terminal)
+    pub final_reset: bool,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct UpdatesConfig {
     pub auto_update: bool,
     pub auto_update_interval: Duration,
+    pub prompt_on_stale: bool,
+    /// The exit code after rendering a page from a stale cache
+    pub stale_exit_code: u8,
+    /// The URL of the pages archive (tar.gz, or ZIP with the `zip-archives`
+    /// feature)
+    pub archive_url: String,
+    /// The User-Agent header of update requests
+    pub user_agent: String,
+    /// Additional headers (name and value) of the requests to the archive URL
+    pub headers: Vec<(String, String)>,
+    /// Whether the pages may be downloaded at all
+    pub networking: bool,
+    /// The age of the cache after which no pages are rendered anymore
+    pub max_cache_age: Option<Duration>,
+    /// The URL of the deltas from the installed pages, `{etag}` expands to
+    /// their version (no delta updates if `None`)
+    pub delta_url: Option<String>,
+}
+
+/// Defaults for a single command, overridden by the command line flags.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CommandConfig {
+    /// The platform to look up the page for (like `--os`)
+    pub platform: Option<OsType>,
+    /// The languages to look up the page in (like `--language`)
+    pub language: Option<String>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct DirectoriesConfig {
     pub custom_pages_dir: Option<PathBuf>,
+    /// The platforms to look up pages for, in order (`None` for "common"),
+    /// empty for the detected platform followed by "common"
+    pub platform_order: Vec<Option<OsType>>,
+    /// Whether the cache is only read, never updated or cleared
+    pub cache_readonly: bool,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -223,31 +730,147 @@ pub struct Config {
     pub display: DisplayConfig,
     pub updates: UpdatesConfig,
     pub directories: DirectoriesConfig,
+    /// Defaults for single commands, by command name
+    pub commands: HashMap<String, CommandConfig>,
 }
 
 impl From<RawConfig> for Config {
     fn from(raw_config: RawConfig) -> Self {
         Self {
-            style: StyleConfig {
-                command_name: raw_config.style.command_name.into(),
-                description: raw_config.style.description.into(),
-                example_text: raw_config.style.example_text.into(),
-                example_code: raw_config.style.example_code.into(),
-                example_variable: raw_config.style.example_variable.into(),
-            },
+            style: raw_config.style.into(),
             display: DisplayConfig {
                 compact: raw_config.display.compact,
                 use_pager: raw_config.display.use_pager,
+                command_prefix: raw_config.display.command_prefix,
+                example_order: raw_config.display.example_order,
+                emphasize_first_example: raw_config.display.emphasize_first_example,
+                wrap_code: raw_config.display.wrap_code,
+                max_width: raw_config.display.max_width.filter(|&width| width > 0),
+                layout: raw_config.display.layout,
+                title_case: raw_config.display.title_case,
+                line_numbering: raw_config.display.line_numbering,
+                code_indent: raw_config
+                    .display
+                    .code_indent
+                    .unwrap_or(DEFAULT_CODE_INDENT),
+                description_indent: raw_config
+                    .display
+                    .description_indent
+                    .unwrap_or(DEFAULT_DESCRIPTION_INDENT),
+                page_url_base: raw_config
+                    .display
+                    .page_url_base
+                    .unwrap_or_else(|| DEFAULT_PAGE_URL_BASE.into()),
+                on_not_found_command: raw_config.display.on_not_found_command,
+                show_resolution_caption: raw_config.display.show_resolution_caption,
+                hyperlink_more_info: raw_config.display.hyperlink_more_info,
+                hyperlink_see_also: raw_config.display.hyperlink_see_also,
+                expand_env: raw_config.display.expand_env,
+                trim_example_whitespace: raw_config.display.trim_example_whitespace.unwrap_or(true),
+                page_separator: raw_config.display.page_separator,
+                no_match_message: raw_config
+                    .display
+                    .no_match_message
+                    .unwrap_or_else(|| DEFAULT_NO_MATCH_MESSAGE.into()),
+                header: raw_config
+                    .display
+                    .header
+                    .filter(|header| !header.is_empty()),
+                footer: raw_config
+                    .display
+                    .footer
+                    .filter(|footer| !footer.is_empty()),
+                dim_header_footer: true,
+                bold_emphasis: true,
+                examples_only: false,
+                line_numbers: false,
+                example_filter: None,
+                dedup_examples: false,
+                requested_command: None,
+                placeholder_values: HashMap::new(),
+                positional_prefix: None,
+                see_also_urls: HashMap::new(),
+                final_reset: false,
             },
             updates: UpdatesConfig {
                 auto_update: raw_config.updates.auto_update,
                 auto_update_interval: Duration::from_secs(
                     raw_config.updates.auto_update_interval_hours * 3600,
                 ),
+                prompt_on_stale: raw_config.updates.prompt_on_stale,
+                stale_exit_code: raw_config.updates.stale_exit_code,
+                archive_url: raw_config
+                    .updates
+                    .archive_url
+                    .filter(|url| !url.is_empty())
+                    .unwrap_or_else(|| DEFAULT_ARCHIVE_URL.into()),
+                user_agent: raw_config
+                    .updates
+                    .user_agent
+                    .filter(|user_agent| !user_agent.trim().is_empty())
+                    .unwrap_or_else(|| DEFAULT_USER_AGENT.into()),
+                headers: raw_config.updates.headers.into_iter().collect(),
+                networking: raw_config.updates.networking.unwrap_or(true),
+                max_cache_age: raw_config
+                    .updates
+                    .max_cache_age_hours
+                    .filter(|&hours| hours > 0)
+                    .map(|hours| Duration::from_secs(hours * 3600)),
+                delta_url: raw_config.updates.delta_url.filter(|url| !url.is_empty()),
             },
             directories: DirectoriesConfig {
                 custom_pages_dir: raw_config.directories.custom_pages_dir,
+                cache_readonly: raw_config.directories.cache_readonly,
+                // The entries are validated when loading the config
+                platform_order: raw_config
+                    .directories
+                    .platform_order
+                    .iter()
+                    .filter_map(|name| match name.as_str() {
+                        "common" => Some(None),
+                        name => OsType::PLATFORMS
+                            .iter()
+                            .find(|os| os.name() == name)
+                            .map(|os| Some(*os)),
+                    })
+                    .collect(),
             },
+            commands: raw_config
+                .commands
+                .into_iter()
+                .map(|(name, command)| {
+                    (
+                        name,
+                        CommandConfig {
+                            platform: command.platform,
+                            language: command.language,
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+impl StyleConfig {
+    /// Return the styles of the default theme, independent of the config
+    /// file, so that the rendered output is reproducible.
+    pub fn fixed_palette() -> Self {
+        Theme::Default.style_config().into()
+    }
+}
+
+impl From<RawStyleConfig> for StyleConfig {
+    fn from(raw_style: RawStyleConfig) -> Self {
+        Self {
+            command_name: raw_style.command_name.unwrap_or_default().into(),
+            description: raw_style.description.unwrap_or_default().into(),
+            example_text: raw_style.example_text.unwrap_or_default().into(),
+            example_code: raw_style.example_code.unwrap_or_default().into(),
+            example_variable: raw_style.example_variable.unwrap_or_default().into(),
+            link: raw_style.link.unwrap_or_default().into(),
+            line_number: raw_style.line_number.unwrap_or_default().into(),
+            synopsis: raw_style.synopsis.unwrap_or_default().into(),
         }
     }
 }
@@ -258,27 +881,141 @@ fn map_io_err_to_config_err(e: IoError) -> TealdeerError {
 }
 
 impl Config {
-    pub fn load(enable_styles: bool) -> Result<Self, TealdeerError> {
+    /// Load the config from `custom_config_path`, or from the config file in
+    /// `custom_config_dir` or the default config directory if `None`.
+    ///
+    /// If set, `theme` takes precedence over the theme in the config file.
+    /// With `strict` (or `strict_config` in the config file), unknown keys in
+    /// the config file are errors.
+    pub fn load(
+        custom_config_path: Option<&Path>,
+        custom_config_dir: Option<&Path>,
+        theme: Option<Theme>,
+        enable_styles: bool,
+        strict: bool,
+    ) -> Result<Self, TealdeerError> {
         debug!("Loading config");
 
         // Determine path
-        let (config_file_path, _) = get_config_path()
+        let (config_file_path, source) = get_config_path(custom_config_path, custom_config_dir)
             .map_err(|e| ConfigError(format!("Could not determine config path: {}", e)))?;
 
+        debug!(
+            "Using config path {} ({})",
+            config_file_path.display(),
+            source
+        );
+
+        // An explicitly specified config file must exist
+        let file_var = source == PathSource::EnvVar
+            && config_file_var().as_deref() == Some(config_file_path.as_path());
+        if (source == PathSource::Cli || file_var) && !config_file_path.is_file() {
+            return Err(ConfigError(format!(
+                "Config file {} does not exist or is not a file",
+                config_file_path.display()
+            )));
+        }
+
         // Load raw config
-        let raw_config: RawConfig = if config_file_path.exists() && config_file_path.is_file() {
+        let mut raw_config: RawConfig = if config_file_path.exists() && config_file_path.is_file() {
             let mut config_file =
                 fs::File::open(config_file_path).map_err(map_io_err_to_config_err)?;
             let mut contents = String::new();
             let _ = config_file
                 .read_to_string(&mut contents)
                 .map_err(map_io_err_to_config_err)?;
-            toml::from_str(&contents)
-                .map_err(|err| ConfigError(format!("Failed to parse config file: {}", err)))?
+            let raw_config: RawConfig = toml::from_str(&contents)
+                .map_err(|err| ConfigError(format!("Failed to parse config file: {}", err)))?;
+            if strict || raw_config.display.strict_config {
+                check_unknown_keys(&contents)?;
+            }
+            raw_config
         } else {
-            RawConfig::new()
+            // Without a config file, use the default theme
+            let mut raw_config = RawConfig::default();
+            raw_config.display.theme = Some(Theme::Default);
+            raw_config
         };
 
+        // The pages are downloaded with HTTP(S) only
+        if let Some(ref url) = raw_config.updates.archive_url {
+            if !url.is_empty() && !url.starts_with("https://") && !url.starts_with("http://") {
+                return Err(ConfigError(format!(
+                    "Invalid archive_url {:?}, only http:// and https:// URLs are supported",
+                    url
+                )));
+            }
+        }
+
+        // The User-Agent is sent as an HTTP header
+        if let Some(ref user_agent) = raw_config.updates.user_agent {
+            if !user_agent
+                .chars()
+                .all(|c| c == '\t' || (' '..='~').contains(&c))
+            {
+                return Err(ConfigError(format!(
+                    "Invalid user_agent {:?}, only printable ASCII characters are allowed",
+                    user_agent
+                )));
+            }
+        }
+
+        // A delta URL without the version would return the same delta for
+        // every version of the installed pages
+        if let Some(ref url) = raw_config.updates.delta_url {
+            if !url.is_empty() && !url.contains("{etag}") {
+                return Err(ConfigError(format!(
+                    "Invalid delta_url {:?}, it must contain {{etag}}",
+                    url
+                )));
+            }
+        }
+
+        // The same goes for the custom headers
+        for (name, value) in &raw_config.updates.headers {
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c))
+            {
+                return Err(ConfigError(format!(
+                    "Invalid header name {:?} in headers",
+                    name
+                )));
+            }
+            if !value.chars().all(|c| c == '\t' || (' '..='~').contains(&c)) {
+                return Err(ConfigError(format!(
+                    "Invalid value of header {:?}, only printable ASCII characters are allowed",
+                    name
+                )));
+            }
+        }
+
+        // Only existing platforms (and "common") can be searched
+        for name in &raw_config.directories.platform_order {
+            if name != "common" && !OsType::PLATFORMS.iter().any(|os| os.name() == name) {
+                return Err(ConfigError(format!(
+                    "Invalid platform {:?} in platform_order, expected one of: {}, common",
+                    name,
+                    OsType::PLATFORMS
+                        .iter()
+                        .map(|os| os.name())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )));
+            }
+        }
+
+        // Apply theme, explicit style entries take precedence
+        if let Some(theme) = theme.or(raw_config.display.theme) {
+            raw_config.style = raw_config.style.or(theme.style_config());
+        }
+
+        // Pick the color variants for the terminal
+        let color_depth = ColorDepth::detect();
+        debug!("Using the colors for color depth {:?}", color_depth);
+        raw_config.style = raw_config.style.for_color_depth(color_depth);
+
         // Convert to config
         let mut config = Self::from(raw_config);
 
@@ -290,21 +1027,151 @@ impl Config {
                 example_text: Style::default(),
                 example_code: Style::default(),
                 example_variable: Style::default(),
+                link: Style::default(),
+                line_number: Style::default(),
+                synopsis: Style::default(),
             };
+            config.display.emphasize_first_example = false;
+            config.display.hyperlink_more_info = false;
+            config.display.hyperlink_see_also = false;
+            config.display.bold_emphasis = false;
+            config.display.dim_header_footer = false;
         }
 
         Ok(config)
     }
 }
 
+/// Return the paths of the keys in `value` (e.g. `display.compact`) that
+/// are missing in `known`.
+fn unknown_keys(value: &toml::Value, known: &toml::Value, path: &str) -> Vec<String> {
+    let (table, known_table) = match (value.as_table(), known.as_table()) {
+        (Some(table), Some(known_table)) => (table, known_table),
+        _ => return Vec::new(),
+    };
+    table
+        .iter()
+        .flat_map(|(key, value)| {
+            let key_path = if path.is_empty() {
+                key.clone()
+            } else {
+                format!("{}.{}", path, key)
+            };
+            match known_table.get(key) {
+                Some(known_value) => unknown_keys(value, known_value, &key_path),
+                None => vec![key_path],
+            }
+        })
+        .collect()
+}
+
+/// Return the (1-based) line of the key `key_path` in the config file
+/// `contents`, if it can be found.
+fn find_key_line(contents: &str, key_path: &str) -> Option<usize> {
+    let mut table = String::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        let path = if line.starts_with('[') {
+            table = line
+                .trim_start_matches('[')
+                .split(']')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                // Quoted keys, e.g. command names with spaces
+                .replace('"', "");
+            table.clone()
+        } else if let Some(pos) = line.find('=') {
+            let key = line[..pos].trim().trim_matches('"');
+            if table.is_empty() {
+                key.to_string()
+            } else {
+                format!("{}.{}", table, key)
+            }
+        } else {
+            continue;
+        };
+        if path == key_path || path.starts_with(&format!("{}.", key_path)) {
+            return Some(i + 1);
+        }
+    }
+    None
+}
+
+/// Return an error for the keys in the config file `contents` that are
+/// ignored when parsing it.
+fn check_unknown_keys(contents: &str) -> Result<(), TealdeerError> {
+    let parse_error =
+        |err: toml::de::Error| ConfigError(format!("Failed to parse config file: {}", err));
+    let value: toml::Value = toml::from_str(contents).map_err(parse_error)?;
+    let mut raw_config: RawConfig = toml::from_str(contents).map_err(parse_error)?;
+
+    // The known keys are the keys of the parsed config. Colors are values
+    // (not tables), but not all of them can be serialized, so they are
+    // replaced by a named color.
+    let named_colors = |style: Option<RawStyle>| {
+        style.map(|style| RawStyle {
+            foreground: style.foreground.map(|_| RawColor::Black),
+            foreground_truecolor: style.foreground_truecolor.map(|_| RawColor::Black),
+            foreground_256: style.foreground_256.map(|_| RawColor::Black),
+            background: style.background.map(|_| RawColor::Black),
+            ..style
+        })
+    };
+    raw_config.style = RawStyleConfig {
+        description: named_colors(raw_config.style.description),
+        command_name: named_colors(raw_config.style.command_name),
+        example_text: named_colors(raw_config.style.example_text),
+        example_code: named_colors(raw_config.style.example_code),
+        example_variable: named_colors(raw_config.style.example_variable),
+        link: named_colors(raw_config.style.link),
+        line_number: named_colors(raw_config.style.line_number),
+        synopsis: named_colors(raw_config.style.synopsis),
+    };
+    let known = toml::Value::try_from(&raw_config)
+        .map_err(|err| ConfigError(format!("Failed to serialize config: {}", err)))?;
+
+    // Sorted by their line, keys that can't be found last
+    let mut keys: Vec<(Option<usize>, String)> = unknown_keys(&value, &known, "")
+        .into_iter()
+        .map(|key| (find_key_line(contents, &key), key))
+        .collect();
+    keys.sort_by_key(|(line, _)| line.unwrap_or(usize::MAX));
+    let keys: Vec<String> = keys
+        .into_iter()
+        .map(|(line, key)| {
+            line.map_or_else(
+                || format!("`{}`", key),
+                |line| format!("`{}` (line {})", key, line),
+            )
+        })
+        .collect();
+    if keys.is_empty() {
+        Ok(())
+    } else {
+        Err(ConfigError(format!(
+            "Unknown config key{} {}",
+            if keys.len() == 1 { "" } else { "s" },
+            keys.join(", ")
+        )))
+    }
+}
+
 /// Return the path to the config directory.
 ///
-/// The config dir path can be overridden using the `TEALDEER_CONFIG_DIR` env
-/// variable. Otherwise, the user config directory is returned.
+/// The config dir path can be overridden using `custom_config_dir` (through
+/// the `--config-dir` flag) or the `TEALDEER_CONFIG_DIR` env variable, in that
+/// order. Otherwise, the user config directory is returned.
 ///
 /// Note that this function does not verify whether the directory at that
 /// location exists, or is a directory.
-pub fn get_config_dir() -> Result<(PathBuf, PathSource), TealdeerError> {
+pub fn get_config_dir(
+    custom_config_dir: Option<&Path>,
+) -> Result<(PathBuf, PathSource), TealdeerError> {
+    if let Some(path) = custom_config_dir {
+        return Ok((path.to_path_buf(), PathSource::Cli));
+    }
+
     // Allow overriding the config directory by setting the
     // $TEALDEER_CONFIG_DIR env variable.
     if let Ok(value) = env::var("TEALDEER_CONFIG_DIR") {
@@ -312,27 +1179,234 @@ pub fn get_config_dir() -> Result<(PathBuf, PathSource), TealdeerError> {
     };
 
     // Otherwise, fall back to the user config directory.
-    match get_app_root(AppDataType::UserConfig, &crate::APP_INFO) {
-        Ok(dirs) => Ok((dirs, PathSource::OsConvention)),
-        Err(_) => Err(ConfigError(
+    match paths::default_dir(DirKind::Config) {
+        Some(dirs) => Ok((dirs, PathSource::OsConvention)),
+        None => Err(ConfigError(
             "Could not determine the user config directory.".into(),
         )),
     }
 }
 
+/// Return the config file set through the `TEALDEER_CONFIG` env variable, if
+/// it is set and not empty.
+fn config_file_var() -> Option<PathBuf> {
+    env::var_os("TEALDEER_CONFIG")
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
 /// Return the path to the config file.
 ///
+/// If `custom_config_path` is set (through the `--config` flag), it takes
+/// precedence over the config directory (see [`get_config_dir`]). Without
+/// `custom_config_dir`, the `TEALDEER_CONFIG` env variable comes next, before
+/// the `TEALDEER_CONFIG_DIR` env variable.
+///
 /// Note that this function does not verify whether the file at that location
 /// exists, or is a file.
-pub fn get_config_path() -> Result<(PathBuf, PathSource), TealdeerError> {
-    let (config_dir, source) = get_config_dir()?;
+pub fn get_config_path(
+    custom_config_path: Option<&Path>,
+    custom_config_dir: Option<&Path>,
+) -> Result<(PathBuf, PathSource), TealdeerError> {
+    if let Some(path) = custom_config_path {
+        return Ok((path.to_path_buf(), PathSource::Cli));
+    }
+    if let (Some(path), None) = (config_file_var(), custom_config_dir) {
+        return Ok((path, PathSource::EnvVar));
+    }
+    let (config_dir, source) = get_config_dir(custom_config_dir)?;
     let config_file_path = config_dir.join(CONFIG_FILE_NAME);
     Ok((config_file_path, source))
 }
 
+/// Return the explanation of a section (`key` is `None`) or a key in the seed
+/// config.
+fn seed_comment(section: &str, key: Option<&str>) -> Option<&'static str> {
+    let comment = match (section, key) {
+        ("style.description", None) => "The style of the page description",
+        ("style.command_name", None) => "The style of the command name in the examples",
+        ("style.example_text", None) => "The style of the example descriptions",
+        ("style.example_code", None) => "The style of the example code",
+        ("style.example_variable", None) => "The style of the placeholders in the example code",
+        ("style.link", None) => "The style of URLs in the descriptions",
+        ("style.line_number", None) => "The style of the line numbers (with --line-numbers)",
+        ("style.synopsis", None) => "The style of the synopsis (the \"Usage:\" line)",
+        (_, Some("foreground")) => {
+            "Text color (`background` for the background color): black, red, green, yellow, \
+             blue, purple, cyan, white, { ansi = 0-255 } or { rgb = { r = 0-255, g = 0-255, b = 0-255 } }"
+        }
+        (_, Some("underline")) => "Underline the text",
+        (_, Some("bold")) => "Use a bold font",
+        (_, Some("attributes")) => {
+            "Additional attributes: bold, dim, italic, underline, blink, reverse, strikethrough"
+        }
+        ("display", None) => "How pages are rendered",
+        ("display", Some("compact")) => "Omit the empty lines of a page",
+        ("display", Some("use_pager")) => "Always page the output (like --pager)",
+        ("display", Some("command_prefix")) => "Text in front of the example code (e.g. \"$ \")",
+        ("display", Some("theme")) => {
+            "Built-in styles, overridden by the [style] sections: \
+             default, ocean, monochrome, high-contrast"
+        }
+        ("display", Some("example_order")) => "Order of the examples: source, alphabetical",
+        ("display", Some("emphasize_first_example")) => "Render the first example in bold",
+        ("display", Some("wrap_code")) => "Wrap long example code to the terminal width",
+        ("display", Some("max_width")) => {
+            "Maximum width to wrap and lay out for, on wider terminals (0 = terminal width)"
+        }
+        ("display", Some("layout")) => {
+            "Example layout: stacked, inline (description and code on one line)"
+        }
+        ("display", Some("title_case")) => {
+            "Case of the page titles: source, lower, command (the requested command)"
+        }
+        ("display", Some("line_numbering")) => {
+            "Numbering of the code lines with --line-numbers: page, example"
+        }
+        ("display", Some("code_indent")) => "Number of spaces before the example code",
+        ("display", Some("description_indent")) => {
+            "Number of spaces before the description and the example descriptions"
+        }
+        ("display", Some("page_url_base")) => "Base URL printed by --page-url",
+        ("display", Some("on_not_found_command")) => {
+            "Shell command run when a page is not found (not set by default)"
+        }
+        ("display", Some("show_resolution_caption")) => {
+            "Print the language and platform of the page below it, e.g. \"(en, common)\""
+        }
+        ("display", Some("hyperlink_more_info")) => {
+            "Make the \"More information\" URL of pages clickable (in supporting terminals)"
+        }
+        ("display", Some("hyperlink_see_also")) => {
+            "Link the commands of the \"See also\" line to their pages (like --page-url)"
+        }
+        ("display", Some("trim_example_whitespace")) => {
+            "Trim trailing whitespace from the example code lines"
+        }
+        ("display", Some("expand_env")) => {
+            "Replace $VAR and ${VAR} in the example code with the environment variables"
+        }
+        ("display", Some("page_separator")) => {
+            "Line above each page with --manifest, {name} is the command \
+             (a rule with the name by default)"
+        }
+        ("display", Some("no_match_message")) => {
+            "Message printed when no examples match --grep, {pattern} is the pattern"
+        }
+        ("display", Some("header")) => {
+            "Line above each rendered page, {name} and {platform} are the page name and \
+             platform (none if empty)"
+        }
+        ("display", Some("footer")) => "Line below each rendered page, like the header",
+        ("display", Some("strict_config")) => "Treat unknown keys in this file as errors",
+        ("updates", None) => "How the cache is updated",
+        ("updates", Some("auto_update")) => "Update the cache automatically when it is outdated",
+        ("updates", Some("auto_update_interval_hours")) => {
+            "Age of the cache (in hours) after which it is updated automatically"
+        }
+        ("updates", Some("prompt_on_stale")) => "Ask whether to update a cache older than 30 days",
+        ("updates", Some("stale_exit_code")) => {
+            "Exit code after showing a page from a stale cache (0 to keep success)"
+        }
+        ("updates", Some("archive_url")) => {
+            "URL of the pages archive (tar.gz, or ZIP with the zip-archives feature)"
+        }
+        ("updates", Some("user_agent")) => "User-Agent header of update requests",
+        ("updates.headers", None) => "Additional headers of the requests to the archive URL",
+        ("updates", Some("networking")) => "Allow downloading the pages",
+        ("updates", Some("max_cache_age_hours")) => {
+            "Age of the cache (in hours) after which no pages are rendered (0 = no limit)"
+        }
+        ("updates", Some("delta_url")) => {
+            "URL of the deltas from the installed pages, {etag} is their version \
+             (no delta updates if empty)"
+        }
+        (section, None) if section.starts_with("commands.") => {
+            "Defaults for a single command, overridden by --os and --language (an example)"
+        }
+        (section, Some("platform")) if section.starts_with("commands.") => {
+            "Look up the page for this platform"
+        }
+        (section, Some("language")) if section.starts_with("commands.") => {
+            "Look up the page in these languages (comma separated)"
+        }
+        ("directories", None) => "Where tealdeer looks for files",
+        ("directories", Some("custom_pages_dir")) => "Directory of custom pages and patches",
+        ("directories", Some("platform_order")) => {
+            "Platforms to look up pages for, in order (\"common\" for the common pages), \
+             unless --os is used"
+        }
+        ("directories", Some("cache_readonly")) => {
+            "Never update or clear the cache (for a shared cache provisioned by an admin)"
+        }
+        _ => return None,
+    };
+    Some(comment)
+}
+
+/// Return the seed config with every key set to its default value and
+/// explained. All keys except the theme are commented out (without a theme,
+/// the pages are not styled).
+///
+/// The keys are taken from the serialized default config, so that new keys
+/// are listed automatically (`test_seed_comments` checks that they are
+/// explained).
+fn seed_template() -> Result<String, TealdeerError> {
+    let serialized = toml::to_string(&RawConfig::complete())
+        .map_err(|err| ConfigError(format!("Failed to serialize default config: {}", err)))?;
+
+    let mut template = String::from(
+        "# tealdeer config file, see https://dbrgn.github.io/tealdeer/config.html\n\
+         #\n\
+         # All keys are set to their default values, and all of them except `theme`\n\
+         # are commented out. To change a key, uncomment it together with its\n\
+         # section header.\n",
+    );
+    let mut section = String::new();
+    let mut explained_style_keys = Vec::new();
+    for line in serialized.lines().filter(|line| !line.is_empty()) {
+        let key = line.find(" = ").map(|pos| &line[..pos]);
+        if line.starts_with('[') {
+            section = line.trim_matches(|c| c == '[' || c == ']').to_string();
+            template.push('\n');
+        }
+        // The style keys are only explained in the first style section
+        let explained = match key {
+            Some(key) if section.starts_with("style.") => {
+                let explained = explained_style_keys.contains(&key);
+                explained_style_keys.push(key);
+                explained
+            }
+            _ => false,
+        };
+        if let (Some(comment), false) = (seed_comment(&section, key), explained) {
+            template.push_str(&format!("## {}\n", comment));
+        }
+        if section == "display" && key.map_or(true, |key| key == "theme") {
+            template.push_str(&format!("{}\n", line));
+        } else {
+            template.push_str(&format!("# {}\n", line));
+        }
+    }
+    Ok(template)
+}
+
 /// Create default config file.
-pub fn make_default_config() -> Result<PathBuf, TealdeerError> {
-    let (config_dir, _) = get_config_dir()?;
+///
+/// The file is created at the same path that [`Config::load`] reads from. An
+/// existing file is only overwritten if `force` is set. Unless `minimal` is
+/// set, the file lists all keys, mostly commented out.
+pub fn make_default_config(
+    custom_config_path: Option<&Path>,
+    custom_config_dir: Option<&Path>,
+    force: bool,
+    minimal: bool,
+) -> Result<PathBuf, TealdeerError> {
+    let (config_file_path, _) = get_config_path(custom_config_path, custom_config_dir)?;
+    let config_dir = config_file_path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
 
     // Ensure that config directory exists
     if !config_dir.exists() {
@@ -349,18 +1423,30 @@ pub fn make_default_config() -> Result<PathBuf, TealdeerError> {
         )));
     }
 
-    // Ensure that a config file doesn't get overwritten
-    let config_file_path = config_dir.join(CONFIG_FILE_NAME);
-    if config_file_path.is_file() {
-        return Err(ConfigError(format!(
-            "A configuration file already exists at {}, no action was taken.",
-            config_file_path.to_str().unwrap()
-        )));
+    // Ensure that a config file doesn't get overwritten unintentionally
+    if config_file_path.exists() {
+        if !config_file_path.is_file() {
+            return Err(ConfigError(format!(
+                "{} already exists but is not a file, no action was taken.",
+                config_file_path.to_string_lossy()
+            )));
+        }
+        if !force {
+            return Err(ConfigError(format!(
+                "A configuration file already exists at {}, no action was taken. \
+                 Use --force to overwrite it.",
+                config_file_path.to_string_lossy()
+            )));
+        }
     }
 
     // Create default config
-    let serialized_config = toml::to_string(&RawConfig::new())
-        .map_err(|err| ConfigError(format!("Failed to serialize default config: {}", err)))?;
+    let serialized_config = if minimal {
+        toml::to_string(&RawConfig::new())
+            .map_err(|err| ConfigError(format!("Failed to serialize default config: {}", err)))?
+    } else {
+        seed_template()?
+    };
 
     // Write default config
     let mut config_file = fs::File::create(&config_file_path).map_err(map_io_err_to_config_err)?;
@@ -378,3 +1464,185 @@ fn test_serialize_deserialize() {
     let deserialized: RawConfig = toml::from_str(&serialized).unwrap();
     assert_eq!(raw_config, deserialized);
 }
+
+#[test]
+fn test_seed_comments() {
+    // Every section and key is explained
+    let serialized = toml::to_string(&RawConfig::complete()).unwrap();
+    let mut section = "";
+    for line in serialized.lines() {
+        if line.starts_with('[') {
+            section = line.trim_matches(|c| c == '[' || c == ']');
+            assert!(seed_comment(section, None).is_some(), "{}", section);
+        } else if let Some(pos) = line.find(" = ") {
+            let key = &line[..pos];
+            assert!(
+                seed_comment(section, Some(key)).is_some(),
+                "{}.{}",
+                section,
+                key
+            );
+        }
+    }
+
+    // Uncommenting all sections and keys gives the complete default config
+    let uncommented: String = seed_template()
+        .unwrap()
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with("##") && line != &"#")
+        .map(|line| format!("{}\n", line.trim_start_matches("# ")))
+        .filter(|line| line.starts_with('[') || line.contains(" = "))
+        .collect();
+    assert!(check_unknown_keys(&uncommented).is_ok());
+    let raw_config: RawConfig = toml::from_str(&uncommented).unwrap();
+    assert_eq!(raw_config, RawConfig::complete());
+}
+
+#[test]
+fn test_unknown_keys() {
+    let invalid = "[display]\ncompact = true\ncompcat = true\n\n\
+                   [style.command_name]\nforeground = { rgb = { r = 1, g = 2, b = 3 } }\n\
+                   bold = true\nblod = true\n\n[stlye.example_text]\nbold = true\n";
+    let err = check_unknown_keys(invalid).unwrap_err();
+    assert_eq!(
+        err.message(),
+        "Unknown config keys `display.compcat` (line 3), \
+         `style.command_name.blod` (line 8), `stlye` (line 10)"
+    );
+
+    let valid = "[display]\ncompact = true\ntheme = 'ocean'\n\
+                 [style.command_name]\nforeground = { ansi = 14 }\n\
+                 [style.link]\nbackground = 'red'\n\
+                 [directories]\ncustom_pages_dir = '/tmp'\n";
+    assert!(check_unknown_keys(valid).is_ok());
+
+    // The seed config is valid
+    let seed = toml::to_string(&RawConfig::new()).unwrap();
+    assert!(check_unknown_keys(&seed).is_ok());
+
+    // Command names are not checked, their keys are
+    let commands = "[commands.git]\nplatform = 'linux'\n\
+                    [commands.\"docker compose\"]\nlanguage = 'de'\nplatfrom = 'osx'\n";
+    assert_eq!(
+        check_unknown_keys(commands).unwrap_err().message(),
+        "Unknown config key `commands.docker compose.platfrom` (line 5)"
+    );
+}
+
+#[test]
+fn test_style_attributes() {
+    let raw_config: RawConfig = toml::from_str(
+        "[style.example_code]\nforeground = 'red'\nattributes = ['italic', 'dim', 'strikethrough']",
+    )
+    .unwrap();
+    let style = Style::from(raw_config.style.example_code.unwrap());
+    assert_eq!(style, Color::Red.italic().dimmed().strikethrough());
+
+    let error = toml::from_str::<RawConfig>("[style.link]\nattributes = ['bold', 'blod']")
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("unknown variant `blod`"), "{}", error);
+    assert!(error.contains("`style.link.attributes`"), "{}", error);
+}
+
+#[test]
+fn test_user_agent() {
+    let config = Config::from(RawConfig::new());
+    assert_eq!(config.updates.user_agent, DEFAULT_USER_AGENT);
+    assert!(DEFAULT_USER_AGENT.starts_with("tealdeer/"));
+
+    let raw_config: RawConfig = toml::from_str("[updates]\nuser_agent = ''").unwrap();
+    assert_eq!(
+        Config::from(raw_config).updates.user_agent,
+        DEFAULT_USER_AGENT
+    );
+
+    let raw_config: RawConfig = toml::from_str("[updates]\nuser_agent = 'mirror-client'").unwrap();
+    assert_eq!(Config::from(raw_config).updates.user_agent, "mirror-client");
+}
+
+#[test]
+fn test_headers() {
+    assert!(Config::from(RawConfig::new()).updates.headers.is_empty());
+
+    let raw_config: RawConfig = toml::from_str(
+        "[updates.headers]
+X-Mirror = 'eu'
+Authorization = 'Bearer abc'",
+    )
+    .unwrap();
+    assert_eq!(
+        Config::from(raw_config).updates.headers,
+        vec![
+            ("Authorization".to_string(), "Bearer abc".to_string()),
+            ("X-Mirror".to_string(), "eu".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_theme_style_overrides() {
+    let mut raw_config: RawConfig =
+        toml::from_str("[display]\ntheme = 'monochrome'\n[style.command_name]\nunderline = true")
+            .unwrap();
+    assert_eq!(raw_config.display.theme, Some(Theme::Monochrome));
+
+    let theme_styles = Theme::Monochrome.style_config();
+    raw_config.style = raw_config.style.or(Theme::Monochrome.style_config());
+    assert_eq!(raw_config.style.example_text, theme_styles.example_text);
+    assert_eq!(
+        raw_config.style.command_name,
+        Some(RawStyle {
+            underline: true,
+            ..RawStyle::default()
+        })
+    );
+}
+
+#[test]
+fn test_color_depth() {
+    assert_eq!(
+        ColorDepth::from_env(Some("truecolor"), Some("xterm")),
+        ColorDepth::TrueColor
+    );
+    assert_eq!(
+        ColorDepth::from_env(None, Some("xterm-256color")),
+        ColorDepth::Ansi256
+    );
+    assert_eq!(ColorDepth::from_env(None, Some("xterm")), ColorDepth::Basic);
+    assert_eq!(ColorDepth::from_env(None, None), ColorDepth::Basic);
+
+    let raw_config: RawConfig = toml::from_str(
+        "[style.example_code]\nforeground = 'cyan'\n\
+         foreground_256 = { ansi = 45 }\n\
+         foreground_truecolor = { rgb = { r = 0, g = 200, b = 255 } }\n\
+         [style.link]\nforeground = 'blue'\nforeground_256 = { ansi = 33 }\n",
+    )
+    .unwrap();
+    let foreground = |depth| {
+        let style = raw_config.style.clone().for_color_depth(depth);
+        (
+            style.example_code.unwrap().foreground,
+            style.link.unwrap().foreground,
+        )
+    };
+    assert_eq!(
+        foreground(ColorDepth::TrueColor),
+        (
+            Some(RawColor::Rgb {
+                r: 0,
+                g: 200,
+                b: 255
+            }),
+            Some(RawColor::Ansi(33))
+        )
+    );
+    assert_eq!(
+        foreground(ColorDepth::Ansi256),
+        (Some(RawColor::Ansi(45)), Some(RawColor::Ansi(33)))
+    );
+    assert_eq!(
+        foreground(ColorDepth::Basic),
+        (Some(RawColor::Cyan), Some(RawColor::Blue))
+    );
+}
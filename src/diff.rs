@@ -0,0 +1,153 @@
+//! A minimal line based diff, used to compare custom pages with upstream pages.
+
+use std::cmp;
+use std::fmt::Write;
+
+/// Number of unchanged lines shown around a change.
+const CONTEXT_LINES: usize = 3;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum DiffLine<'a> {
+    Unchanged(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+impl<'a> DiffLine<'a> {
+    fn is_change(self) -> bool {
+        !matches!(self, Self::Unchanged(_))
+    }
+
+    fn in_old(self) -> bool {
+        !matches!(self, Self::Added(_))
+    }
+
+    fn in_new(self) -> bool {
+        !matches!(self, Self::Removed(_))
+    }
+}
+
+/// Compute the line changes between `old` and `new` through their longest
+/// common subsequence.
+fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<DiffLine<'a>> {
+    // `lcs[i][j]` is the length of the longest common subsequence of
+    // `old[i..]` and `new[j..]`.
+    let mut lcs = vec![vec![0_usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                cmp::max(lcs[i + 1][j], lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::with_capacity(cmp::max(old.len(), new.len()));
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            lines.push(DiffLine::Unchanged(old[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            lines.push(DiffLine::Removed(old[i]));
+            i += 1;
+        } else {
+            lines.push(DiffLine::Added(new[j]));
+            j += 1;
+        }
+    }
+    lines.extend(old[i..].iter().map(|line| DiffLine::Removed(line)));
+    lines.extend(new[j..].iter().map(|line| DiffLine::Added(line)));
+    lines
+}
+
+/// Format a hunk range (1-based start line and line count).
+fn hunk_range(lines_before: usize, count: usize) -> String {
+    // By convention, empty ranges refer to the line before the hunk.
+    let start = if count == 0 {
+        lines_before
+    } else {
+        lines_before + 1
+    };
+    format!("{},{}", start, count)
+}
+
+/// Return a unified diff between `old` and `new`, or `None` if they are equal.
+pub fn unified_diff(old: &str, new: &str, old_name: &str, new_name: &str) -> Option<String> {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let lines = diff_lines(&old_lines, &new_lines);
+
+    let changes: Vec<usize> = (0..lines.len()).filter(|&i| lines[i].is_change()).collect();
+    if changes.is_empty() {
+        return None;
+    }
+
+    let mut output = format!("--- {}\n+++ {}\n", old_name, new_name);
+
+    // Group changes that are close enough to share their context lines
+    let mut group_start = 0;
+    for k in 0..changes.len() {
+        let is_last = k + 1 == changes.len();
+        if !is_last && changes[k + 1] - changes[k] <= 2 * CONTEXT_LINES {
+            continue;
+        }
+
+        let start = changes[group_start].saturating_sub(CONTEXT_LINES);
+        let end = cmp::min(changes[k] + CONTEXT_LINES + 1, lines.len());
+        let hunk = &lines[start..end];
+
+        let old_before = lines[..start].iter().filter(|line| line.in_old()).count();
+        let new_before = lines[..start].iter().filter(|line| line.in_new()).count();
+        let old_count = hunk.iter().filter(|line| line.in_old()).count();
+        let new_count = hunk.iter().filter(|line| line.in_new()).count();
+        let _ = writeln!(
+            output,
+            "@@ -{} +{} @@",
+            hunk_range(old_before, old_count),
+            hunk_range(new_before, new_count)
+        );
+        for line in hunk {
+            let _ = match line {
+                DiffLine::Unchanged(text) => writeln!(output, " {}", text),
+                DiffLine::Removed(text) => writeln!(output, "-{}", text),
+                DiffLine::Added(text) => writeln!(output, "+{}", text),
+            };
+        }
+
+        group_start = k + 1;
+    }
+
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::unified_diff;
+
+    #[test]
+    fn test_equal() {
+        assert_eq!(unified_diff("a\nb\n", "a\nb\n", "old", "new"), None);
+    }
+
+    #[test]
+    fn test_single_change() {
+        let diff = unified_diff("a\nb\nc\n", "a\nx\nc\n", "old", "new").unwrap();
+        assert_eq!(diff, "--- old\n+++ new\n@@ -1,3 +1,3 @@\n a\n-b\n+x\n c\n");
+    }
+
+    #[test]
+    fn test_separate_hunks() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n";
+        let new = "0\n1\n2\n3\n4\n5\n6\n7\n8\n9\n";
+        let diff = unified_diff(old, new, "old", "new").unwrap();
+        assert_eq!(
+            diff,
+            "--- old\n+++ new\n\
+             @@ -1,3 +1,4 @@\n+0\n 1\n 2\n 3\n\
+             @@ -7,4 +8,3 @@\n 7\n 8\n 9\n-10\n"
+        );
+    }
+}
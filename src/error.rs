@@ -19,6 +19,35 @@ impl TealdeerError {
             | Self::WriteError(msg) => msg,
         }
     }
+
+    /// The exit code to use when exiting because of this error.
+    pub fn exit_code(&self) -> ExitCode {
+        match self {
+            Self::CacheError(_) => ExitCode::CacheMissing,
+            Self::ConfigError(_) => ExitCode::ConfigError,
+            Self::UpdateError(_) => ExitCode::UpdateError,
+            Self::WriteError(_) => ExitCode::Failure,
+        }
+    }
+}
+
+/// The exit codes of tealdeer.
+///
+/// These are a stable contract for scripts, don't change existing values.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ExitCode {
+    /// The page was rendered, or the requested action succeeded
+    Success = 0,
+    /// The page was not found
+    PageNotFound = 1,
+    /// Any other error, e.g. invalid arguments or the output couldn't be written
+    Failure = 2,
+    /// The cache is missing or couldn't be accessed
+    CacheMissing = 3,
+    /// The config couldn't be loaded or created
+    ConfigError = 4,
+    /// The cache couldn't be updated, e.g. because of a network error
+    UpdateError = 5,
 }
 
 impl From<ReqwestError> for TealdeerError {
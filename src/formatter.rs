@@ -1,41 +1,113 @@
 //! Functions related to formatting and printing lines from a `Tokenizer`.
 
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::env;
 use std::io::{BufRead, Write};
+use std::iter;
+use std::ops::Range;
 
-use ansi_term::{ANSIString, ANSIStrings};
+use ansi_term::{ANSIString, ANSIStrings, Style};
 use log::debug;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthChar;
 
-use crate::config::Config;
+use crate::config::{Config, ExampleOrder, Layout, LineNumbering, StyleConfig, TitleCase};
 use crate::error::TealdeerError::{self, WriteError};
 use crate::tokenizer::Tokenizer;
-use crate::types::LineType;
+use crate::types::{LineType, OsType, MORE_INFO_PREFIX, SEE_ALSO_PREFIX, SYNOPSIS_PREFIX};
+
+/// Additional indentation of wrapped example code lines.
+const CONTINUATION_INDENT: usize = 4;
+/// Space between the example description and the example code with the
+/// inline layout.
+const INLINE_GAP: &str = "  ";
+/// Minimum width of the example code line numbers (with `--line-numbers`).
+const LINE_NUMBER_WIDTH: usize = 2;
+/// Replaces the end of truncated text.
+const ELLIPSIS: char = '\u{2026}';
+/// Resets all styles, ends the page with `final_reset`.
+const RESET: &str = "\x1b[0m";
+
+/// A synthetic page exercising every styled element, rendered with
+/// `--theme-preview`.
+pub const THEME_PREVIEW_PAGE: &str = "\
+# preview
+
+> A synthetic page to preview the styles of tealdeer.
+> More information: <https://github.com/dbrgn/tealdeer>.
+> Usage: preview [--verbose] [--input <file>] [<output>]
+
+- Run the command:
+
+`preview`
+
+- Pass a file as a placeholder:
+
+`preview --input {{path/to/file}}`
+
+- Mix options and placeholders (see https://tldr.sh):
+
+`preview --verbose {{path/to/input}} {{path/to/output}}`
+";
+
+/// Platform dependent rendering settings of a page.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct PlatformRendering {
+    /// Whether command names are matched case insensitively when highlighting.
+    ignore_command_case: bool,
+}
+
+impl PlatformRendering {
+    /// Return the rendering settings for pages of `platform` (`None` for
+    /// common pages).
+    fn new(platform: Option<OsType>) -> Self {
+        Self {
+            // Commands are case insensitive in both cmd and PowerShell
+            ignore_command_case: platform == Some(OsType::Windows),
+        }
+    }
+}
 
 fn highlight_command<'a>(
     command: &'a str,
     example_code: &'a str,
+    rendering: PlatformRendering,
     config: &Config,
     parts: &mut Vec<ANSIString<'a>>,
 ) {
+    // ASCII lowercasing keeps the byte offsets intact
+    let (haystack, needle) = if rendering.ignore_command_case {
+        (
+            Cow::Owned(example_code.to_ascii_lowercase()),
+            Cow::Owned(command.to_ascii_lowercase()),
+        )
+    } else {
+        (Cow::Borrowed(example_code), Cow::Borrowed(command))
+    };
+
     let mut code_part_end_pos = 0;
-    while let Some(command_start) = example_code[code_part_end_pos..].find(&command) {
+    while let Some(command_start) = haystack[code_part_end_pos..].find(needle.as_ref()) {
         let code_part = &example_code[code_part_end_pos..code_part_end_pos + command_start];
+        let command_pos = code_part_end_pos + command_start;
+        let command_text = &example_code[command_pos..command_pos + needle.len()];
         parts.push(config.style.example_code.paint(code_part));
         if code_part_end_pos == 0 {
             // Only highlight command names at the start of the line ...
-            parts.push(config.style.command_name.paint(command));
+            parts.push(config.style.command_name.paint(command_text));
         } else {
             let char_before_command = example_code
                 .chars()
                 .nth(code_part_end_pos + command_start - 1);
             if char_before_command.filter(|c| c.is_whitespace()).is_some() {
                 // ... or when preceded by a whitespace character.
-                parts.push(config.style.command_name.paint(command));
+                parts.push(config.style.command_name.paint(command_text));
             } else {
-                parts.push(config.style.example_code.paint(command));
+                parts.push(config.style.example_code.paint(command_text));
             }
         }
 
-        code_part_end_pos += command_start + command.len();
+        code_part_end_pos += command_start + command_text.len();
     }
     parts.push(
         config
@@ -45,37 +117,905 @@ fn highlight_command<'a>(
     );
 }
 
+/// Return the number of columns of `text`.
+///
+/// Every grapheme cluster (e.g. a letter with combining marks, or an emoji
+/// sequence joined by zero-width joiners) is as wide as its widest character,
+/// so that wide characters (e.g. CJK or emoji) take up two columns.
+fn text_width(text: &str) -> usize {
+    text.graphemes(true).map(grapheme_width).sum()
+}
+
+/// Return the number of columns of a single grapheme cluster.
+fn grapheme_width(grapheme: &str) -> usize {
+    grapheme
+        .chars()
+        .map(|c| c.width().unwrap_or(0))
+        .max()
+        .unwrap_or(0)
+}
+
+/// Truncate `text` to at most `width` columns, replacing the end with an
+/// ellipsis if it doesn't fit.
+///
+/// The text is only cut between grapheme clusters, so that no character (or
+/// combining mark) is cut in half.
+pub fn truncate(text: &str, width: usize) -> Cow<str> {
+    if text_width(text) <= width {
+        return Cow::Borrowed(text);
+    }
+    if width == 0 {
+        return Cow::Borrowed("");
+    }
+
+    let mut truncated = String::new();
+    let mut columns = 0;
+    for grapheme in text.graphemes(true) {
+        columns += grapheme_width(grapheme);
+        if columns > width - 1 {
+            break;
+        }
+        truncated.push_str(grapheme);
+    }
+    truncated.push(ELLIPSIS);
+    Cow::Owned(truncated)
+}
+
+/// Return the number of columns of example code, once rendered.
+fn code_width(text: &str) -> usize {
+    text_width(&format_code_unstyled(text))
+}
+
+/// Split example code into lines that fit into `width` columns (including the
+/// indentation of `indent` spaces and the command prefix).
+///
+/// Lines are only split at whitespace outside of placeholders, words that are
+/// too long for a line are not split.
+fn wrap_code<'a>(text: &'a str, width: usize, indent: usize, prefix: &str) -> Vec<&'a str> {
+    // The byte ranges of the words, separated by whitespace outside of placeholders
+    let mut words = Vec::new();
+    let mut word_start = None;
+    let mut in_placeholder = false;
+    for (i, c) in text.char_indices() {
+        if c.is_whitespace() && !in_placeholder {
+            if let Some(start) = word_start.take() {
+                words.push(start..i);
+            }
+            continue;
+        }
+        word_start.get_or_insert(i);
+        if text[i..].starts_with("{{") {
+            in_placeholder = true;
+        } else if text[i..].starts_with("}}") {
+            in_placeholder = false;
+        }
+    }
+    if let Some(start) = word_start {
+        words.push(start..text.len());
+    }
+
+    let mut lines = Vec::new();
+    let mut available = width.saturating_sub(indent + text_width(prefix));
+    let mut line: Option<Range<usize>> = None;
+    for word in words {
+        line = match line {
+            Some(current) if code_width(&text[current.start..word.end]) > available => {
+                lines.push(&text[current]);
+                available = width.saturating_sub(indent + CONTINUATION_INDENT);
+                Some(word)
+            }
+            Some(current) => Some(current.start..word.end),
+            None => Some(word),
+        };
+    }
+    match line {
+        Some(current) => lines.push(&text[current]),
+        None => lines.push(text),
+    }
+    lines
+}
+
+/// Split example code into lines if enabled and `width` is set, see
+/// `wrap_code`.
+fn code_lines<'a>(text: &'a str, width: Option<usize>, config: &Config) -> Vec<&'a str> {
+    match width {
+        Some(width) if config.display.wrap_code => wrap_code(
+            text,
+            width,
+            config.display.code_indent,
+            &config.display.command_prefix,
+        ),
+        _ => vec![text],
+    }
+}
+
+/// Return the width of the example description column, if all examples of
+/// `tokens` fit into `width` columns (including the indentation of `indent`
+/// spaces and the command prefix) with the inline layout.
+///
+/// Every example description has to be followed by its example code.
+fn inline_column_width(
+    tokens: &[LineType],
+    width: usize,
+    indent: usize,
+    prefix: &str,
+) -> Option<usize> {
+    let mut examples = Vec::new();
+    let mut example_text = None;
+    for token in tokens {
+        match token {
+            LineType::ExampleText(text) if example_text.is_none() => example_text = Some(text),
+            LineType::ExampleCode(code) => examples.push((example_text.take()?, code)),
+            LineType::ExampleText(_) => return None,
+            _ => {}
+        }
+    }
+    if example_text.is_some() {
+        return None;
+    }
+
+    let column = examples.iter().map(|(text, _)| text_width(text)).max()?;
+    let fits = examples.iter().all(|(_, code)| {
+        indent + column + INLINE_GAP.len() + text_width(prefix) + code_width(code) <= width
+    });
+    Some(column).filter(|_| fits)
+}
+
+/// Return the width of the example description column if the page is
+/// rendered with the inline layout, see `inline_column_width`.
+fn inline_column(tokens: &[LineType], width: Option<usize>, config: &Config) -> Option<usize> {
+    match (config.display.layout, width) {
+        (Layout::Inline, Some(width)) => inline_column_width(
+            tokens,
+            width,
+            config.display.description_indent,
+            &config.display.command_prefix,
+        ),
+        _ => None,
+    }
+}
+
+/// Return the byte ranges of the http(s) URLs in `text`, ignoring
+/// placeholders in {{ curly braces }}.
+fn find_urls(text: &str) -> Vec<Range<usize>> {
+    let mut urls = Vec::new();
+    let mut pos = 0;
+    while pos < text.len() {
+        let rest = &text[pos..];
+        if rest.starts_with("{{") {
+            pos += rest.find("}}").map_or(rest.len(), |end| end + 2);
+            continue;
+        }
+
+        let at_word_start = text[..pos]
+            .chars()
+            .next_back()
+            .map_or(true, |c| !c.is_alphanumeric());
+        if at_word_start && (rest.starts_with("https://") || rest.starts_with("http://")) {
+            let end = rest
+                .find(|c: char| c.is_whitespace() || matches!(c, '<' | '>' | '"' | '{' | '}'))
+                .unwrap_or_else(|| rest.len());
+            // Trailing punctuation usually belongs to the sentence
+            let url = rest[..end]
+                .trim_end_matches(|c| matches!(c, '.' | ',' | ';' | ':' | '!' | '?' | ')'));
+            if !url.ends_with("//") {
+                urls.push(pos..pos + url.len());
+                pos += url.len();
+                continue;
+            }
+        }
+        pos += rest.chars().next().map_or(1, char::len_utf8);
+    }
+    urls
+}
+
+/// Format text in `style`, with URLs in the link style.
+fn format_text(text: &str, style: Style, config: &Config) -> String {
+    let mut parts = Vec::new();
+    let mut text_start = 0;
+    for url in find_urls(text) {
+        parts.push(style.paint(&text[text_start..url.start]));
+        parts.push(config.style.link.paint(&text[url.start..url.end]));
+        text_start = url.end;
+    }
+    parts.push(style.paint(&text[text_start..]));
+
+    ANSIStrings(&parts).to_string()
+}
+
+/// An inline span of a description, see `description_spans`.
+#[derive(Debug, PartialEq, Eq)]
+enum Span<'a> {
+    Text(&'a str),
+    /// Inline code in `backticks`
+    Code(&'a str),
+    /// Text in *asterisks* (or **double asterisks**)
+    Emphasis(&'a str),
+}
+
+impl<'a> Span<'a> {
+    /// The text of the span, without the delimiters.
+    fn text(&self) -> &'a str {
+        match self {
+            Self::Text(text) | Self::Code(text) | Self::Emphasis(text) => text,
+        }
+    }
+}
+
+/// Return the text between `delimiter` at the start of `text` and the next
+/// `delimiter`, if it isn't empty.
+fn delimited<'a>(text: &'a str, delimiter: &str) -> Option<&'a str> {
+    let rest = text.strip_prefix(delimiter)?;
+    let content = &rest[..rest.find(delimiter)?];
+    Some(content).filter(|content| !content.is_empty())
+}
+
+/// Split a description into text, inline code and emphasis spans. Delimiters
+/// without a matching closing delimiter are kept as text, as well as
+/// asterisks around whitespace (e.g. `a * b * c`).
+fn description_spans(text: &str) -> Vec<Span<'_>> {
+    let mut spans = Vec::new();
+    let mut text_start = 0;
+    let mut pos = 0;
+    while pos < text.len() {
+        let rest = &text[pos..];
+        let code = delimited(rest, "`").map(|code| (Span::Code(code), code.len() + 2));
+        let span = code.or_else(|| {
+            ["**", "*"].iter().find_map(|delimiter| {
+                delimited(rest, delimiter)
+                    .filter(|emphasis| emphasis.trim() == *emphasis)
+                    .map(|emphasis| {
+                        let len = emphasis.len() + 2 * delimiter.len();
+                        (Span::Emphasis(emphasis), len)
+                    })
+            })
+        });
+        if let Some((span, len)) = span {
+            if text_start < pos {
+                spans.push(Span::Text(&text[text_start..pos]));
+            }
+            spans.push(span);
+            pos += len;
+            text_start = pos;
+        } else {
+            pos += rest.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    if text_start < text.len() {
+        spans.push(Span::Text(&text[text_start..]));
+    }
+    spans
+}
+
+/// Format a description line, with inline code in the example code style,
+/// emphasis in bold and URLs in the link style.
+fn format_description(text: &str, config: &Config) -> String {
+    let style = config.style.description;
+    description_spans(text)
+        .into_iter()
+        .map(|span| match span {
+            Span::Text(text) => format_text(text, style, config),
+            Span::Code(code) => config.style.example_code.paint(code).to_string(),
+            Span::Emphasis(emphasis) if config.display.bold_emphasis => {
+                style.bold().paint(emphasis).to_string()
+            }
+            Span::Emphasis(emphasis) => style.paint(emphasis).to_string(),
+        })
+        .collect()
+}
+
+/// Return a description line without the inline markup delimiters, see
+/// `description_spans`.
+fn format_description_unstyled(text: &str) -> String {
+    description_spans(text).iter().map(Span::text).collect()
+}
+
+/// Format the "More information" line of a page, with the URL in the link
+/// style. The URL is wrapped in an OSC 8 escape sequence if
+/// `hyperlink_more_info` is set, so that terminals make it clickable.
+fn format_more_info(url: &str, config: &Config) -> String {
+    let style = config.style.description;
+    let link = if config.display.hyperlink_more_info {
+        format!("\x1b]8;;{0}\x1b\\{0}\x1b]8;;\x1b\\", url)
+    } else {
+        url.to_string()
+    };
+    let prefix = format!("{} <", MORE_INFO_PREFIX);
+    ANSIStrings(&[
+        style.paint(prefix),
+        config.style.link.paint(link),
+        style.paint(">."),
+    ])
+    .to_string()
+}
+
+/// Format the "See also" line of a page, with the commands in the command
+/// name style. Commands with an URL in `see_also_urls` are wrapped in an OSC 8
+/// escape sequence.
+fn format_see_also(commands: &[String], config: &Config) -> String {
+    let style = config.style.description;
+    let mut parts = vec![style.paint(format!("{} ", SEE_ALSO_PREFIX))];
+    for (i, command) in commands.iter().enumerate() {
+        if i > 0 {
+            parts.push(style.paint(", "));
+        }
+        let name = match config.display.see_also_urls.get(command) {
+            Some(url) => format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, command),
+            None => command.clone(),
+        };
+        parts.push(config.style.command_name.paint(name));
+    }
+    parts.push(style.paint("."));
+    ANSIStrings(&parts).to_string()
+}
+
+/// Format the "Usage" line of a page, with the synopsis in the synopsis style.
+fn format_synopsis(synopsis: &str, config: &Config) -> String {
+    ANSIStrings(&[
+        config
+            .style
+            .description
+            .paint(format!("{} ", SYNOPSIS_PREFIX)),
+        config.style.synopsis.paint(synopsis),
+    ])
+    .to_string()
+}
+
 /// Format and highlight code examples including variables in {{ curly braces }}.
-fn format_code(command: &str, text: &str, config: &Config) -> String {
+///
+/// With `with_prefix`, the command prefix is prepended.
+fn format_code(
+    command: &str,
+    text: &str,
+    with_prefix: bool,
+    rendering: PlatformRendering,
+    config: &Config,
+) -> String {
     let mut parts = Vec::new();
+    if with_prefix && !config.display.command_prefix.is_empty() {
+        parts.push(
+            config
+                .style
+                .example_code
+                .paint(&config.display.command_prefix),
+        );
+    }
     for between_variables in text.split("}}") {
         if let Some(variable_start) = between_variables.find("{{") {
             let example_code = &between_variables[..variable_start];
             let example_variable = &between_variables[variable_start + 2..];
 
-            highlight_command(&command, &example_code, &config, &mut parts);
+            highlight_command(&command, &example_code, rendering, &config, &mut parts);
             parts.push(config.style.example_variable.paint(example_variable));
         } else {
-            highlight_command(&command, &between_variables, &config, &mut parts);
+            highlight_command(&command, &between_variables, rendering, &config, &mut parts);
         }
     }
 
     ANSIStrings(&parts).to_string()
 }
 
+/// Remove the curly braces around variables without applying any styles.
+fn format_code_unstyled(text: &str) -> String {
+    let mut code = String::with_capacity(text.len());
+    for between_variables in text.split("}}") {
+        if let Some(variable_start) = between_variables.find("{{") {
+            code.push_str(&between_variables[..variable_start]);
+            code.push_str(&between_variables[variable_start + 2..]);
+        } else {
+            code.push_str(between_variables);
+        }
+    }
+    code
+}
+
+/// Replace the placeholders of example code that have a value in `values`
+/// with that value, placeholders without a value are kept.
+fn substitute_placeholders(text: &str, values: &HashMap<String, String>) -> String {
+    let mut code = String::with_capacity(text.len());
+    let mut parts = text.split("}}").peekable();
+    while let Some(between_variables) = parts.next() {
+        // Only parts that are followed by another part were followed by `}}`
+        if parts.peek().is_none() {
+            code.push_str(between_variables);
+            break;
+        }
+        if let Some(variable_start) = between_variables.find("{{") {
+            let variable = &between_variables[variable_start + 2..];
+            code.push_str(&between_variables[..variable_start]);
+            if let Some(value) = values.get(variable) {
+                code.push_str(value);
+            } else {
+                code.push_str("{{");
+                code.push_str(variable);
+                code.push_str("}}");
+            }
+        } else {
+            code.push_str(between_variables);
+            code.push_str("}}");
+        }
+    }
+    code
+}
+
+/// Replace the placeholders of example code with numbered positional
+/// arguments (e.g. `{{file}}` with `$1`), with `prefix` before the number.
+///
+/// `names` are the placeholders of the previous code lines of the example, in
+/// order of appearance. A placeholder that appears again gets the same
+/// number, new placeholders are appended.
+fn positional_placeholders(text: &str, prefix: char, names: &mut Vec<String>) -> String {
+    let mut code = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let len = match rest[start + 2..].find("}}") {
+            Some(len) => len,
+            None => break,
+        };
+        let name = &rest[start + 2..start + 2 + len];
+        if !names.iter().any(|known| known == name) {
+            names.push(name.into());
+        }
+        let number = names.iter().position(|known| known == name).unwrap_or(0) + 1;
+        code.push_str(&rest[..start]);
+        code.push(prefix);
+        code.push_str(&number.to_string());
+        rest = &rest[start + 4 + len..];
+    }
+    code.push_str(rest);
+    code
+}
+
+/// Return whether `name` is a valid environment variable name (letters,
+/// digits and underscores, not starting with a digit).
+fn is_env_var_name(name: &str) -> bool {
+    name.chars()
+        .next()
+        .map_or(false, |c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Return the name of the variable that `text` starts with (`$VAR` or
+/// `${VAR}`), and the length of the reference.
+fn env_var_reference(text: &str) -> (&str, usize) {
+    if let Some(braced) = text.strip_prefix("${") {
+        return braced
+            .find('}')
+            .map_or(("", 0), |end| (&braced[..end], end + 3));
+    }
+    let name_len = text[1..]
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(text.len() - 1);
+    (&text[1..=name_len], name_len + 1)
+}
+
+/// Replace the environment variables (`$VAR` and `${VAR}`) of example code
+/// with the values returned by `lookup`. Undefined variables and the text of
+/// placeholders are kept.
+fn expand_env_vars<F>(text: &str, lookup: F) -> String
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut code = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(pos) = rest.find(|c| c == '$' || c == '{') {
+        code.push_str(&rest[..pos]);
+        rest = &rest[pos..];
+        if rest.starts_with("{{") {
+            let end = rest.find("}}").map_or(rest.len(), |end| end + 2);
+            code.push_str(&rest[..end]);
+            rest = &rest[end..];
+            continue;
+        }
+        if !rest.starts_with('$') {
+            code.push('{');
+            rest = &rest[1..];
+            continue;
+        }
+
+        let (name, len) = env_var_reference(rest);
+        if let Some(value) = Some(name)
+            .filter(|name| is_env_var_name(name))
+            .and_then(&lookup)
+        {
+            code.push_str(&value);
+            rest = &rest[len..];
+        } else {
+            code.push('$');
+            rest = &rest[1..];
+        }
+    }
+    code.push_str(rest);
+    code
+}
+
+/// Sort the examples of a token stream by their description.
+///
+/// The lines before the first example are kept in place, examples are
+/// separated by a single empty line.
+fn sort_examples(tokens: impl Iterator<Item = LineType>) -> Vec<LineType> {
+    let mut header = Vec::new();
+    let mut examples: Vec<Vec<LineType>> = Vec::new();
+    for token in tokens {
+        match (token, examples.last_mut()) {
+            (token @ LineType::ExampleText(_), _) => examples.push(vec![token]),
+            (token, Some(example)) => example.push(token),
+            (token, None) => header.push(token),
+        }
+    }
+
+    for example in &mut examples {
+        while example.last() == Some(&LineType::Empty) {
+            example.pop();
+        }
+    }
+    examples.sort_by_cached_key(|example| match example.first() {
+        Some(LineType::ExampleText(text)) => text.to_lowercase(),
+        _ => String::new(),
+    });
+
+    let separated_examples = examples.into_iter().enumerate().flat_map(|(i, example)| {
+        let separator = if i == 0 { None } else { Some(LineType::Empty) };
+        separator.into_iter().chain(example)
+    });
+    header.into_iter().chain(separated_examples).collect()
+}
+
+/// Return the tokens of a token stream, with the examples in `order`.
+fn ordered_tokens<'a, R: BufRead>(
+    tokenizer: &'a mut Tokenizer<R>,
+    order: ExampleOrder,
+) -> Box<dyn Iterator<Item = LineType> + 'a> {
+    let tokens = iter::from_fn(move || tokenizer.next_token());
+    match order {
+        ExampleOrder::Source => Box::new(tokens),
+        ExampleOrder::Alphabetical => Box::new(sort_examples(tokens).into_iter()),
+    }
+}
+
+/// Remove the description of a page (and the empty lines around it) from
+/// `tokens`, keeping the examples (with `--examples-only`, not in the JSON
+/// output).
+pub fn remove_description(tokens: Vec<LineType>) -> Vec<LineType> {
+    let first_example = tokens
+        .iter()
+        .position(|token| matches!(token, LineType::ExampleText(_)))
+        .unwrap_or_else(|| tokens.len());
+    let mut tokens = tokens.into_iter();
+    let titles: Vec<LineType> = tokens
+        .by_ref()
+        .take(first_example)
+        .filter(|token| matches!(token, LineType::Title(_)))
+        .collect();
+    titles
+        .into_iter()
+        .chain(iter::once(LineType::Empty))
+        .chain(tokens)
+        .collect()
+}
+
+/// Return the title of a page, cased according to the `title_case` of
+/// `config`.
+fn cased_title(title: String, config: &Config) -> String {
+    match (config.display.title_case, &config.display.requested_command) {
+        (TitleCase::Lower, _) => title.to_lowercase(),
+        (TitleCase::Command, Some(command)) => command.clone(),
+        (TitleCase::Source, _) | (TitleCase::Command, None) => title,
+    }
+}
+
+/// Return the tokens of a token stream to render, according to `config`.
+pub fn page_tokens<R: BufRead>(tokenizer: &mut Tokenizer<R>, config: &Config) -> Vec<LineType> {
+    let values = &config.display.placeholder_values;
+    // The placeholders of the current example, numbered by their position
+    let mut positional_names = Vec::new();
+    ordered_tokens(tokenizer, config.display.example_order)
+        .map(|token| match token {
+            LineType::Title(title) => LineType::Title(cased_title(title, config)),
+            LineType::ExampleCode(text) if !values.is_empty() => {
+                LineType::ExampleCode(substitute_placeholders(&text, values))
+            }
+            token => token,
+        })
+        .map(|token| match token {
+            LineType::ExampleCode(text) if config.display.expand_env => {
+                LineType::ExampleCode(expand_env_vars(&text, |name| env::var(name).ok()))
+            }
+            token => token,
+        })
+        .map(|token| match (token, config.display.positional_prefix) {
+            (LineType::ExampleText(text), Some(_)) => {
+                positional_names.clear();
+                LineType::ExampleText(text)
+            }
+            (LineType::ExampleCode(text), Some(prefix)) => LineType::ExampleCode(
+                positional_placeholders(&text, prefix, &mut positional_names),
+            ),
+            (token, _) => token,
+        })
+        .collect()
+}
+
+/// Remove the examples whose description doesn't contain `pattern` (ignoring
+/// case) from `tokens`. Return the remaining tokens and the number of removed
+/// examples.
+fn filter_examples(tokens: Vec<LineType>, pattern: &str) -> (Vec<LineType>, usize) {
+    let pattern = pattern.to_lowercase();
+    let mut keep = true;
+    let mut hidden = 0;
+    let mut filtered: Vec<LineType> = tokens
+        .into_iter()
+        .filter(|token| {
+            if let LineType::ExampleText(text) = token {
+                keep = text.to_lowercase().contains(&pattern);
+                if !keep {
+                    hidden += 1;
+                }
+            }
+            keep
+        })
+        .collect();
+    // The empty lines after the last shown example are not needed
+    if hidden > 0 {
+        while let Some(LineType::Empty) = filtered.last() {
+            filtered.pop();
+        }
+    }
+    (filtered, hidden)
+}
+
+/// Return the code of an example line with the placeholders left out and the
+/// whitespace collapsed, to compare examples that differ only in them.
+fn normalized_code(text: &str) -> String {
+    let mut code = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let len = match rest[start + 2..].find("}}") {
+            Some(len) => len,
+            None => break,
+        };
+        code.push_str(&rest[..start]);
+        code.push_str("{{}}");
+        rest = &rest[start + 4 + len..];
+    }
+    code.push_str(rest);
+    code.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Remove the examples of `tokens` whose code is the same as that of an
+/// earlier example after `normalized_code`, the first one is kept. Return the
+/// remaining tokens and the number of removed examples.
+fn dedup_examples(tokens: Vec<LineType>) -> (Vec<LineType>, usize) {
+    let mut seen = Vec::new();
+    let mut collapsed = 0;
+    let mut deduped = Vec::with_capacity(tokens.len());
+    // The tokens of the current example, from its description on
+    let mut example = Vec::new();
+    let mut finish_example = |example: &mut Vec<LineType>, deduped: &mut Vec<LineType>| {
+        let key: Vec<String> = example
+            .iter()
+            .filter_map(|token| match token {
+                LineType::ExampleCode(text) => Some(normalized_code(text)),
+                _ => None,
+            })
+            .collect();
+        if key.is_empty() || !seen.contains(&key) {
+            deduped.append(example);
+            seen.push(key);
+        } else {
+            example.clear();
+            collapsed += 1;
+        }
+    };
+    for token in tokens {
+        if let LineType::ExampleText(_) = token {
+            finish_example(&mut example, &mut deduped);
+        }
+        if example.is_empty() && !matches!(token, LineType::ExampleText(_)) {
+            deduped.push(token);
+        } else {
+            example.push(token);
+        }
+    }
+    finish_example(&mut example, &mut deduped);
+    // The empty lines after the last shown example are not needed
+    if collapsed > 0 {
+        while let Some(LineType::Empty) = deduped.last() {
+            deduped.pop();
+        }
+    }
+    (deduped, collapsed)
+}
+
+/// Return the tokens to render, the number of examples hidden by `--grep` and
+/// the number of duplicate examples collapsed by `--dedup`.
+fn rendered_tokens<R: BufRead>(
+    tokenizer: &mut Tokenizer<R>,
+    config: &Config,
+) -> (Vec<LineType>, usize, usize) {
+    let tokens = page_tokens(tokenizer, config);
+    let tokens = if config.display.examples_only {
+        remove_description(tokens)
+    } else {
+        tokens
+    };
+    let (tokens, hidden) = match config.display.example_filter {
+        Some(ref pattern) => filter_examples(tokens, pattern),
+        None => (tokens, 0),
+    };
+    if config.display.dedup_examples {
+        let (tokens, collapsed) = dedup_examples(tokens);
+        (tokens, hidden, collapsed)
+    } else {
+        (tokens, hidden, 0)
+    }
+}
+
+/// Return the number of examples of a token stream that are rendered (with
+/// `--grep`, only the matching examples).
+pub fn rendered_example_count<R: BufRead>(tokenizer: &mut Tokenizer<R>, config: &Config) -> usize {
+    rendered_tokens(tokenizer, config)
+        .0
+        .iter()
+        .filter(|token| matches!(token, LineType::ExampleText(_)))
+        .count()
+}
+
+/// Write a note that `hidden` examples were hidden by `--grep` and that
+/// `collapsed` duplicate examples were collapsed by `--dedup`, if any.
+fn write_filter_note<T: Write>(
+    writer: &mut T,
+    hidden: usize,
+    collapsed: usize,
+    config: &Config,
+) -> Result<(), TealdeerError> {
+    if collapsed > 0 {
+        let (noun, verb) = if collapsed == 1 {
+            ("example", "was")
+        } else {
+            ("examples", "were")
+        };
+        writeln!(
+            writer,
+            "\n{}{} duplicate {} {} collapsed.",
+            " ".repeat(config.display.description_indent),
+            collapsed,
+            noun,
+            verb
+        )
+        .map_err(|e| WriteError(e.to_string()))?;
+    }
+    if let (Some(pattern), true) = (&config.display.example_filter, hidden > 0) {
+        let (noun, verb) = if hidden == 1 {
+            ("example", "was")
+        } else {
+            ("examples", "were")
+        };
+        writeln!(
+            writer,
+            "\n{}{} {} not matching {:?} {} hidden.",
+            " ".repeat(config.display.description_indent),
+            hidden,
+            noun,
+            pattern,
+            verb
+        )
+        .map_err(|e| WriteError(e.to_string()))?;
+    }
+    Ok(())
+}
+
+/// The numbers of the example code lines, with `--line-numbers`.
+struct LineNumbers {
+    enabled: bool,
+    per_example: bool,
+    next: usize,
+}
+
+impl LineNumbers {
+    fn new(config: &Config) -> Self {
+        Self {
+            enabled: config.display.line_numbers,
+            per_example: config.display.line_numbering == LineNumbering::Example,
+            next: 1,
+        }
+    }
+
+    /// Return the number of columns taken by the line numbers.
+    fn width(&self) -> usize {
+        if self.enabled {
+            LINE_NUMBER_WIDTH + 1
+        } else {
+            0
+        }
+    }
+
+    /// Start a new example.
+    fn start_example(&mut self) {
+        if self.per_example {
+            self.next = 1;
+        }
+    }
+
+    /// Return the label of the next example code line (empty if disabled).
+    fn next_label(&mut self) -> String {
+        if !self.enabled {
+            return String::new();
+        }
+        let label = format!("{:>width$} ", self.next, width = LINE_NUMBER_WIDTH);
+        self.next += 1;
+        label
+    }
+
+    /// Return the label of the next example code line in `style`.
+    fn next_styled_label(&mut self, style: Style) -> String {
+        if !self.enabled {
+            return String::new();
+        }
+        style.paint(self.next_label()).to_string()
+    }
+}
+
+/// Return the styles in bold, to emphasize an example.
+fn emphasize(style: StyleConfig) -> StyleConfig {
+    StyleConfig {
+        description: style.description.bold(),
+        command_name: style.command_name.bold(),
+        example_text: style.example_text.bold(),
+        example_code: style.example_code.bold(),
+        example_variable: style.example_variable.bold(),
+        link: style.link.bold(),
+        line_number: style.line_number,
+        synopsis: style.synopsis,
+    }
+}
+
 /// Print a token stream to an ANSI terminal.
+///
+/// `platform` is the platform of the page (`None` for common pages), it is
+/// used to pick platform dependent rendering defaults. `width` is the width of
+/// the terminal, if known. It is used to wrap example code and to lay out the
+/// examples inline, if enabled.
 pub fn print_lines<T, R>(
     writer: &mut T,
     tokenizer: &mut Tokenizer<R>,
+    platform: Option<OsType>,
+    width: Option<usize>,
     config: &Config,
 ) -> Result<(), TealdeerError>
 where
     T: Write,
     R: BufRead,
 {
+    let rendering = PlatformRendering::new(platform);
+    let emphasized_config = Config {
+        style: emphasize(config.style),
+        ..config.clone()
+    };
+    let mut line_numbers = LineNumbers::new(config);
+    let width = width.map(|width| width.saturating_sub(line_numbers.width()));
+    let (tokens, hidden, collapsed) = rendered_tokens(tokenizer, config);
+    let column = inline_column(&tokens, width, config);
+    let description_indent = " ".repeat(config.display.description_indent);
+    let code_indent = " ".repeat(config.display.code_indent);
+    let continuation_indent = " ".repeat(config.display.code_indent + CONTINUATION_INDENT);
+    let mut example_count = 0;
     let mut command = String::new();
-    while let Some(token) = tokenizer.next_token() {
+    let mut pending_example_text = None;
+    for token in tokens {
+        if let LineType::ExampleText(_) = token {
+            example_count += 1;
+            line_numbers.start_example();
+        }
+        let example_config = if config.display.emphasize_first_example && example_count == 1 {
+            &emphasized_config
+        } else {
+            config
+        };
+
         match token {
+            // With the inline layout, the example code follows on the same line
+            LineType::Empty if pending_example_text.is_some() => {}
             LineType::Empty => {
                 if !config.display.compact {
                     writeln!(writer).map_err(|e| WriteError(e.to_string()))?;
@@ -90,19 +1030,617 @@ where
                 debug!("Detected command name: {}", &command);
             }
             LineType::Description(text) => {
-                writeln!(writer, "  {}", config.style.description.paint(text))
+                writeln!(
+                    writer,
+                    "{}{}",
+                    description_indent,
+                    format_description(&text, config)
+                )
+                .map_err(|e| WriteError(e.to_string()))?;
+            }
+            LineType::MoreInfo(url) => {
+                writeln!(
+                    writer,
+                    "{}{}",
+                    description_indent,
+                    format_more_info(&url, config)
+                )
+                .map_err(|e| WriteError(e.to_string()))?;
+            }
+            LineType::SeeAlso(commands) => {
+                writeln!(
+                    writer,
+                    "{}{}",
+                    description_indent,
+                    format_see_also(&commands, config)
+                )
+                .map_err(|e| WriteError(e.to_string()))?;
+            }
+            LineType::Synopsis(synopsis) => {
+                writeln!(
+                    writer,
+                    "{}{}",
+                    description_indent,
+                    format_synopsis(&synopsis, config)
+                )
+                .map_err(|e| WriteError(e.to_string()))?;
+            }
+            LineType::ExampleText(text) if column.is_some() => {
+                pending_example_text = Some(text);
+            }
+            LineType::ExampleText(text) => {
+                writeln!(
+                    writer,
+                    "{}{}",
+                    description_indent,
+                    format_text(&text, example_config.style.example_text, example_config)
+                )
+                .map_err(|e| WriteError(e.to_string()))?;
+            }
+            LineType::ExampleCode(text) => {
+                if let (Some(column), Some(example_text)) = (column, pending_example_text.take()) {
+                    writeln!(
+                        writer,
+                        "{}{}{:padding$}{}{}{}",
+                        description_indent,
+                        format_text(
+                            &example_text,
+                            example_config.style.example_text,
+                            example_config
+                        ),
+                        "",
+                        INLINE_GAP,
+                        line_numbers.next_styled_label(config.style.line_number),
+                        &format_code(&command, &text, true, rendering, example_config),
+                        padding = column - text_width(&example_text),
+                    )
+                    .map_err(|e| WriteError(e.to_string()))?;
+                    continue;
+                }
+                for (i, line) in code_lines(&text, width, config).into_iter().enumerate() {
+                    let indent = if i == 0 {
+                        &code_indent
+                    } else {
+                        &continuation_indent
+                    };
+                    writeln!(
+                        writer,
+                        "{}{}{}",
+                        indent,
+                        line_numbers.next_styled_label(config.style.line_number),
+                        &format_code(&command, line, i == 0, rendering, example_config)
+                    )
                     .map_err(|e| WriteError(e.to_string()))?;
+                }
+            }
+            // Language hints are only part of the JSON output
+            LineType::CodeLanguage(_) => {}
+            LineType::Other(text) => debug!("Unknown line type: {:?}", text),
+        }
+    }
+    write_filter_note(writer, hidden, collapsed, config)?;
+    writeln!(writer).map_err(|e| WriteError(e.to_string()))?;
+
+    // Captured output (e.g. in a file) shouldn't leave the terminal styled
+    // when it is printed
+    if config.display.final_reset {
+        write!(writer, "{}", RESET).map_err(|e| WriteError(e.to_string()))?;
+    }
+    Ok(())
+}
+
+/// Print a token stream without any styling.
+///
+/// This produces the same output as `print_lines` with all styles disabled,
+/// but skips the styling machinery entirely.
+pub fn print_lines_unstyled<T, R>(
+    writer: &mut T,
+    tokenizer: &mut Tokenizer<R>,
+    width: Option<usize>,
+    config: &Config,
+) -> Result<(), TealdeerError>
+where
+    T: Write,
+    R: BufRead,
+{
+    let mut line_numbers = LineNumbers::new(config);
+    let width = width.map(|width| width.saturating_sub(line_numbers.width()));
+    let (tokens, hidden, collapsed) = rendered_tokens(tokenizer, config);
+    let column = inline_column(&tokens, width, config);
+    let description_indent = " ".repeat(config.display.description_indent);
+    let code_indent = " ".repeat(config.display.code_indent);
+    let continuation_indent = " ".repeat(config.display.code_indent + CONTINUATION_INDENT);
+    let mut pending_example_text = None;
+    for token in tokens {
+        if let LineType::ExampleText(_) = token {
+            line_numbers.start_example();
+        }
+        match token {
+            // With the inline layout, the example code follows on the same line
+            LineType::Empty if pending_example_text.is_some() => {}
+            LineType::Empty => {
+                if !config.display.compact {
+                    writeln!(writer).map_err(|e| WriteError(e.to_string()))?;
+                }
+            }
+            LineType::Title(_) => debug!("Ignoring title"),
+            LineType::ExampleText(text) if column.is_some() => {
+                pending_example_text = Some(text);
+            }
+            LineType::Description(text) => {
+                writeln!(
+                    writer,
+                    "{}{}",
+                    description_indent,
+                    format_description_unstyled(&text)
+                )
+                .map_err(|e| WriteError(e.to_string()))?;
             }
             LineType::ExampleText(text) => {
-                writeln!(writer, "  {}", config.style.example_text.paint(text))
+                writeln!(writer, "{}{}", description_indent, text)
                     .map_err(|e| WriteError(e.to_string()))?;
             }
+            LineType::MoreInfo(url) => {
+                writeln!(
+                    writer,
+                    "{}{} <{}>.",
+                    description_indent, MORE_INFO_PREFIX, url
+                )
+                .map_err(|e| WriteError(e.to_string()))?;
+            }
+            LineType::SeeAlso(commands) => {
+                writeln!(
+                    writer,
+                    "{}{} {}.",
+                    description_indent,
+                    SEE_ALSO_PREFIX,
+                    commands.join(", ")
+                )
+                .map_err(|e| WriteError(e.to_string()))?;
+            }
+            LineType::Synopsis(synopsis) => {
+                writeln!(
+                    writer,
+                    "{}{} {}",
+                    description_indent, SYNOPSIS_PREFIX, synopsis
+                )
+                .map_err(|e| WriteError(e.to_string()))?;
+            }
             LineType::ExampleCode(text) => {
-                writeln!(writer, "      {}", &format_code(&command, &text, &config))
+                if let (Some(column), Some(example_text)) = (column, pending_example_text.take()) {
+                    writeln!(
+                        writer,
+                        "{}{}{:padding$}{}{}{}{}",
+                        description_indent,
+                        example_text,
+                        "",
+                        INLINE_GAP,
+                        line_numbers.next_label(),
+                        config.display.command_prefix,
+                        format_code_unstyled(&text),
+                        padding = column - text_width(&example_text),
+                    )
+                    .map_err(|e| WriteError(e.to_string()))?;
+                    continue;
+                }
+                for (i, line) in code_lines(&text, width, config).into_iter().enumerate() {
+                    let (indent, prefix) = if i == 0 {
+                        (&code_indent, config.display.command_prefix.as_str())
+                    } else {
+                        (&continuation_indent, "")
+                    };
+                    writeln!(
+                        writer,
+                        "{}{}{}{}",
+                        indent,
+                        line_numbers.next_label(),
+                        prefix,
+                        format_code_unstyled(line)
+                    )
                     .map_err(|e| WriteError(e.to_string()))?;
+                }
             }
+            // Language hints are only part of the JSON output
+            LineType::CodeLanguage(_) => {}
             LineType::Other(text) => debug!("Unknown line type: {:?}", text),
         }
     }
+    write_filter_note(writer, hidden, collapsed, config)?;
     writeln!(writer).map_err(|e| WriteError(e.to_string()))
 }
+
+/// Return the first description line of a token stream.
+pub fn first_description<R: BufRead>(tokenizer: &mut Tokenizer<R>) -> Option<String> {
+    while let Some(token) = tokenizer.next_token() {
+        match token {
+            LineType::Description(text) => return Some(text),
+            LineType::ExampleText(_) | LineType::ExampleCode(_) => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Return the first example of a token stream, without any styling.
+pub fn first_example<R: BufRead>(tokenizer: &mut Tokenizer<R>) -> Option<String> {
+    while let Some(token) = tokenizer.next_token() {
+        if let LineType::ExampleCode(text) = token {
+            return Some(format_code_unstyled(&text));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Span::{Code, Emphasis, Text};
+    use super::{
+        code_width, dedup_examples, description_spans, expand_env_vars, filter_examples, find_urls,
+        format_code_unstyled, inline_column_width, positional_placeholders, remove_description,
+        sort_examples, substitute_placeholders, text_width, truncate, wrap_code,
+    };
+    use crate::types::LineType;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_description_spans() {
+        assert_eq!(
+            description_spans("Run `ls` in *any* **directory**."),
+            vec![
+                Text("Run "),
+                Code("ls"),
+                Text(" in "),
+                Emphasis("any"),
+                Text(" "),
+                Emphasis("directory"),
+                Text("."),
+            ]
+        );
+        // Unmatched and spaced delimiters are text
+        assert_eq!(
+            description_spans("Matches *.txt and a * b * c, `unterminated"),
+            vec![Text("Matches *.txt and a * b * c, `unterminated")]
+        );
+        assert_eq!(description_spans("``"), vec![Text("``")]);
+    }
+
+    #[test]
+    fn test_wrap_code() {
+        let text = "tar cf {{path/to/target.tar}} {{file1 file2}} --verbose";
+        assert_eq!(wrap_code(text, 80, 6, ""), vec![text]);
+        // 6 columns indentation, 10 for continuation lines
+        assert_eq!(
+            wrap_code(text, 40, 6, ""),
+            vec!["tar cf {{path/to/target.tar}}", "{{file1 file2}} --verbose"]
+        );
+        assert_eq!(
+            wrap_code(text, 30, 6, "$ "),
+            vec![
+                "tar cf",
+                "{{path/to/target.tar}}",
+                "{{file1 file2}}",
+                "--verbose"
+            ]
+        );
+        // Words are not split, even if they are too long
+        assert_eq!(wrap_code("a {{b c}}", 1, 6, ""), vec!["a", "{{b c}}"]);
+        assert_eq!(wrap_code("", 10, 6, ""), vec![""]);
+    }
+
+    #[test]
+    fn test_wrap_code_grapheme_clusters() {
+        // Letters with combining marks are a single column each
+        assert_eq!(code_width("cafe\u{301} {{fichier}}"), 12);
+        let hebrew = "\u{5e9}\u{5b8}\u{5c1}\u{5dc}\u{5d5}\u{5b9}\u{5dd}";
+        assert_eq!(code_width(hebrew), 4);
+        let arabic =
+            "\u{646}\u{64e}\u{635}\u{651} \u{639}\u{64e}\u{631}\u{64e}\u{628}\u{650}\u{64a}\u{651}";
+        assert_eq!(code_width(arabic), 7);
+
+        let first = format!("echo {{{{{}}}}}", arabic);
+        let second = format!("{{{{{}}}}}", hebrew);
+        let text = format!("{} {}", first, second);
+        assert_eq!(code_width(&text), 17);
+        assert_eq!(wrap_code(&text, 23, 6, ""), vec![text.as_str()]);
+        assert_eq!(
+            wrap_code(&text, 22, 6, ""),
+            vec![first.as_str(), second.as_str()]
+        );
+    }
+
+    #[test]
+    fn test_wrap_code_wide_characters() {
+        // CJK characters and emoji are two columns wide
+        assert_eq!(text_width("\u{76ee}\u{6807}"), 4);
+        assert_eq!(text_width("\u{1f4e6}"), 2);
+        // Zero-width joiners don't add any columns
+        assert_eq!(text_width("\u{1f469}\u{200d}\u{1f4bb}"), 2);
+        assert_eq!(text_width("\u{200d}"), 0);
+
+        let text = "tar cf {{\u{76ee}\u{6807}.tar}} {{\u{1f4e6}}}";
+        assert_eq!(code_width(text), 18);
+        assert_eq!(wrap_code(text, 24, 6, ""), vec![text]);
+        assert_eq!(
+            wrap_code(text, 23, 6, ""),
+            vec!["tar cf {{\u{76ee}\u{6807}.tar}}", "{{\u{1f4e6}}}"]
+        );
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(
+            truncate("tar  Archiving utility.", 30),
+            "tar  Archiving utility."
+        );
+        assert_eq!(
+            truncate("tar  Archiving utility.", 12),
+            "tar  Archiv\u{2026}"
+        );
+        assert_eq!(truncate("tar", 0), "");
+        // Wide characters are not cut in half
+        assert_eq!(
+            truncate("\u{76ee}\u{6807}\u{6587}\u{4ef6}", 6),
+            "\u{76ee}\u{6807}\u{2026}"
+        );
+        assert_eq!(
+            truncate("\u{76ee}\u{6807}\u{6587}\u{4ef6}", 5),
+            "\u{76ee}\u{6807}\u{2026}"
+        );
+        // Neither are grapheme clusters
+        assert_eq!(
+            truncate("cafe\u{301} cre\u{300}me", 6),
+            "cafe\u{301} \u{2026}"
+        );
+        assert_eq!(truncate("ab\u{1f469}\u{200d}\u{1f4bb}cd", 4), "ab\u{2026}");
+    }
+
+    #[test]
+    fn test_inline_column_width() {
+        let tokens = vec![
+            LineType::Title("tar".into()),
+            LineType::Empty,
+            LineType::Description("Archiving utility.".into()),
+            LineType::Empty,
+            LineType::ExampleText("Extract:".into()),
+            LineType::Empty,
+            LineType::ExampleCode("tar xf {{source.tar}}".into()),
+            LineType::Empty,
+            LineType::ExampleText("Create an archive:".into()),
+            LineType::Empty,
+            LineType::ExampleCode("tar cf {{target.tar}} {{file}}".into()),
+        ];
+        // 2 columns indentation, 18 for the descriptions, 2 between the
+        // columns and 22 for the longest command
+        assert_eq!(inline_column_width(&tokens, 44, 2, ""), Some(18));
+        assert_eq!(inline_column_width(&tokens, 43, 2, ""), None);
+        assert_eq!(inline_column_width(&tokens, 46, 2, "$ "), Some(18));
+        assert_eq!(inline_column_width(&tokens, 45, 2, "$ "), None);
+
+        // Every description needs its example code
+        let incomplete = &tokens[..tokens.len() - 1];
+        assert_eq!(inline_column_width(incomplete, 80, 2, ""), None);
+        assert_eq!(inline_column_width(&tokens[..4], 80, 2, ""), None);
+    }
+
+    #[test]
+    fn test_substitute_placeholders() {
+        let mut values = HashMap::new();
+        values.insert("file".to_string(), "backup.tar".to_string());
+        values.insert("dir".to_string(), "./data".to_string());
+
+        assert_eq!(
+            substitute_placeholders("tar cf {{file}} {{dir}}", &values),
+            "tar cf backup.tar ./data"
+        );
+        assert_eq!(
+            substitute_placeholders("tar xf {{file}} -C {{path/to/dir}}", &values),
+            "tar xf backup.tar -C {{path/to/dir}}"
+        );
+        // Text without placeholders is kept as is
+        for text in &["echo", "echo }}", "echo {{file", "echo {{}} {{dir}}}}"] {
+            assert_eq!(
+                substitute_placeholders(text, &HashMap::new()),
+                (*text).to_string()
+            );
+        }
+    }
+
+    #[test]
+    fn test_positional_placeholders() {
+        let mut names = Vec::new();
+        assert_eq!(
+            positional_placeholders("cp {{file}} {{dir}}", '$', &mut names),
+            "cp $1 $2"
+        );
+        // Numbered across the code lines of an example
+        assert_eq!(
+            positional_placeholders("  {{dir}}/{{file}}.bak {{mode}}", '%', &mut names),
+            "  %2/%1.bak %3"
+        );
+        assert_eq!(
+            positional_placeholders("echo {{unterminated", '$', &mut Vec::new()),
+            "echo {{unterminated"
+        );
+    }
+
+    #[test]
+    fn test_expand_env_vars() {
+        let lookup = |name: &str| match name {
+            "HOME" => Some("/home/user".to_string()),
+            "TLDR_HOST" => Some("example.com".to_string()),
+            _ => None,
+        };
+        assert_eq!(
+            expand_env_vars("ssh $TLDR_HOST:${HOME}/file", lookup),
+            "ssh example.com:/home/user/file"
+        );
+        // Undefined variables, placeholders and other braces are kept
+        assert_eq!(
+            expand_env_vars("echo $UNDEFINED ${HOME {{$HOME}} ${} $ $1 {a,b}", lookup),
+            "echo $UNDEFINED ${HOME {{$HOME}} ${} $ $1 {a,b}"
+        );
+        assert_eq!(expand_env_vars("cd $HOME/x$", lookup), "cd /home/user/x$");
+    }
+
+    #[test]
+    fn test_remove_description() {
+        let tokens = vec![
+            LineType::Title("tar".into()),
+            LineType::Empty,
+            LineType::Description("Archiving utility.".into()),
+            LineType::Description("Often combined with gzip.".into()),
+            LineType::Empty,
+            LineType::ExampleText("Extract an archive:".into()),
+            LineType::Empty,
+            LineType::ExampleCode("tar xf {{source.tar}}".into()),
+        ];
+        assert_eq!(
+            remove_description(tokens),
+            vec![
+                LineType::Title("tar".into()),
+                LineType::Empty,
+                LineType::ExampleText("Extract an archive:".into()),
+                LineType::Empty,
+                LineType::ExampleCode("tar xf {{source.tar}}".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_filter_examples() {
+        let tokens = || {
+            vec![
+                LineType::Title("tar".into()),
+                LineType::Empty,
+                LineType::Description("Archiving utility.".into()),
+                LineType::Empty,
+                LineType::ExampleText("Extract an archive:".into()),
+                LineType::Empty,
+                LineType::ExampleCode("tar xf {{source.tar}}".into()),
+                LineType::Empty,
+                LineType::ExampleText("Create an archive:".into()),
+                LineType::Empty,
+                LineType::ExampleCode("tar cf {{target.tar}} {{file}}".into()),
+            ]
+        };
+        assert_eq!(
+            filter_examples(tokens(), "EXTRACT"),
+            (
+                vec![
+                    LineType::Title("tar".into()),
+                    LineType::Empty,
+                    LineType::Description("Archiving utility.".into()),
+                    LineType::Empty,
+                    LineType::ExampleText("Extract an archive:".into()),
+                    LineType::Empty,
+                    LineType::ExampleCode("tar xf {{source.tar}}".into()),
+                ],
+                1
+            )
+        );
+        assert_eq!(filter_examples(tokens(), "archive"), (tokens(), 0));
+    }
+
+    #[test]
+    fn test_dedup_examples() {
+        let tokens = vec![
+            LineType::Title("tar".into()),
+            LineType::Empty,
+            LineType::ExampleText("Extract an archive:".into()),
+            LineType::Empty,
+            LineType::ExampleCode("tar xf {{source.tar}}".into()),
+            LineType::Empty,
+            LineType::ExampleText("Create an archive:".into()),
+            LineType::Empty,
+            LineType::ExampleCode("tar cf {{target.tar}} {{file}}".into()),
+            LineType::Empty,
+            LineType::ExampleText("Extract an archive to a directory:".into()),
+            LineType::Empty,
+            LineType::ExampleCode("tar  xf {{path/to/source.tar}}".into()),
+            LineType::Empty,
+        ];
+        assert_eq!(
+            dedup_examples(tokens),
+            (
+                vec![
+                    LineType::Title("tar".into()),
+                    LineType::Empty,
+                    LineType::ExampleText("Extract an archive:".into()),
+                    LineType::Empty,
+                    LineType::ExampleCode("tar xf {{source.tar}}".into()),
+                    LineType::Empty,
+                    LineType::ExampleText("Create an archive:".into()),
+                    LineType::Empty,
+                    LineType::ExampleCode("tar cf {{target.tar}} {{file}}".into()),
+                ],
+                1
+            )
+        );
+    }
+
+    #[test]
+    fn test_sort_examples() {
+        let tokens = vec![
+            LineType::Title("tar".into()),
+            LineType::Empty,
+            LineType::Description("Archiving utility.".into()),
+            LineType::Empty,
+            LineType::ExampleText("Extract an archive:".into()),
+            LineType::Empty,
+            LineType::ExampleCode("tar xf {{source.tar}}".into()),
+            LineType::Empty,
+            LineType::ExampleText("create an archive:".into()),
+            LineType::Empty,
+            LineType::ExampleCode("tar cf {{target.tar}} {{file}}".into()),
+        ];
+        assert_eq!(
+            sort_examples(tokens.into_iter()),
+            vec![
+                LineType::Title("tar".into()),
+                LineType::Empty,
+                LineType::Description("Archiving utility.".into()),
+                LineType::Empty,
+                LineType::ExampleText("create an archive:".into()),
+                LineType::Empty,
+                LineType::ExampleCode("tar cf {{target.tar}} {{file}}".into()),
+                LineType::Empty,
+                LineType::ExampleText("Extract an archive:".into()),
+                LineType::Empty,
+                LineType::ExampleCode("tar xf {{source.tar}}".into()),
+            ]
+        );
+    }
+
+    fn urls(text: &str) -> Vec<&str> {
+        find_urls(text).into_iter().map(|url| &text[url]).collect()
+    }
+
+    #[test]
+    fn test_find_urls() {
+        assert_eq!(
+            urls("More information: <https://example.com/page>."),
+            vec!["https://example.com/page"]
+        );
+        assert_eq!(
+            urls("See http://a.org, or https://b.org/x?y=1."),
+            vec!["http://a.org", "https://b.org/x?y=1"]
+        );
+
+        // Placeholders and URLs inside of words are ignored
+        assert!(urls("Download {{https://example.com/file}}").is_empty());
+        assert!(urls("Use xhttps://example.com or https://").is_empty());
+    }
+
+    #[test]
+    fn test_format_code_unstyled() {
+        assert_eq!(
+            format_code_unstyled("tar -xf {{source.tar}}"),
+            "tar -xf source.tar"
+        );
+        assert_eq!(
+            format_code_unstyled("cp {{a}} {{b}} --force"),
+            "cp a b --force"
+        );
+        assert_eq!(format_code_unstyled("echo }}"), "echo ");
+    }
+}
@@ -0,0 +1,273 @@
+//! A minimal interactive page picker, used with `--interactive`.
+//!
+//! The picker reads keystrokes from the terminal (`/dev/tty`) in raw mode and
+//! draws the list of matching pages below a prompt.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::mem;
+use std::os::unix::io::AsRawFd;
+
+use unicode_width::UnicodeWidthStr;
+
+/// The maximum number of matches that are shown below the prompt.
+const MAX_MATCHES: usize = 10;
+
+/// How long to wait for the rest of an escape sequence, in milliseconds. A
+/// terminal sends a sequence at once, so without more input it was Esc.
+const ESCAPE_TIMEOUT_MS: i32 = 100;
+
+/// Puts the terminal into raw mode, the original mode is restored on drop.
+struct RawMode {
+    fd: i32,
+    original: libc::termios,
+}
+
+impl RawMode {
+    fn enable(tty: &File) -> io::Result<Self> {
+        let fd = tty.as_raw_fd();
+        // Safe, `termios` is a plain C struct that is filled by `tcgetattr`
+        let mut termios: libc::termios = unsafe { mem::zeroed() };
+        if unsafe { libc::tcgetattr(fd, &mut termios) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let original = termios;
+
+        // Read single keystrokes without echo, Ctrl-C is handled as a key
+        termios.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG);
+        termios.c_cc[libc::VMIN] = 1;
+        termios.c_cc[libc::VTIME] = 0;
+        if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &termios) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self { fd, original })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        unsafe { libc::tcsetattr(self.fd, libc::TCSANOW, &self.original) };
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Key {
+    Char(char),
+    Backspace,
+    Up,
+    Down,
+    Enter,
+    /// Ctrl-C, Ctrl-D, Ctrl-G or Esc
+    Cancel,
+    Other,
+}
+
+/// An input that tells whether more input arrives soon, to tell Esc from the
+/// start of an escape sequence.
+trait KeyInput: Read {
+    /// Return whether input is available within `ESCAPE_TIMEOUT_MS`.
+    fn input_pending(&mut self) -> io::Result<bool>;
+}
+
+impl KeyInput for File {
+    fn input_pending(&mut self) -> io::Result<bool> {
+        let mut poll_fd = libc::pollfd {
+            fd: self.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        match unsafe { libc::poll(&mut poll_fd, 1, ESCAPE_TIMEOUT_MS) } {
+            -1 => Err(io::Error::last_os_error()),
+            ready => Ok(ready > 0),
+        }
+    }
+}
+
+fn read_byte<R: Read>(reader: &mut R) -> io::Result<u8> {
+    let mut byte = [0];
+    reader.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+/// Read a keystroke.
+fn read_key<R: KeyInput>(reader: &mut R) -> io::Result<Key> {
+    let key = match read_byte(reader)? {
+        b'\r' | b'\n' => Key::Enter,
+        8 | 127 => Key::Backspace,
+        3 | 4 | 7 => Key::Cancel,
+        16 => Key::Up,
+        14 => Key::Down,
+        27 if !reader.input_pending()? => Key::Cancel,
+        // Arrow keys are sent as `ESC [ A` (up) and `ESC [ B` (down)
+        27 => match read_byte(reader)? {
+            b'[' if reader.input_pending()? => match read_byte(reader)? {
+                b'A' => Key::Up,
+                b'B' => Key::Down,
+                _ => Key::Other,
+            },
+            _ => Key::Other,
+        },
+        byte if byte.is_ascii_graphic() || byte == b' ' => Key::Char(char::from(byte)),
+        // The first byte of a multibyte UTF-8 character tells its length
+        byte if byte >= 0xc0 => {
+            let len = (byte.leading_ones() as usize).min(4);
+            let mut bytes = vec![byte; len];
+            reader.read_exact(&mut bytes[1..])?;
+            String::from_utf8(bytes)
+                .ok()
+                .and_then(|text| text.chars().next())
+                .map_or(Key::Other, Key::Char)
+        }
+        _ => Key::Other,
+    };
+    Ok(key)
+}
+
+/// Return the distance between the first and the last character of `query`
+/// in `name`, if `name` contains the characters of `query` in order (ignoring
+/// case).
+fn match_span(name: &str, query: &str) -> Option<usize> {
+    let mut positions = name.chars().flat_map(char::to_lowercase).enumerate();
+    let mut first = None;
+    let mut last = 0;
+    for query_char in query.chars().flat_map(char::to_lowercase) {
+        let (pos, _) = positions.find(|&(_, c)| c == query_char)?;
+        first.get_or_insert(pos);
+        last = pos;
+    }
+    Some(first.map_or(0, |first| last - first))
+}
+
+/// Return the names that match `query`, the closest matches first. Without a
+/// query, all names are returned in their order.
+fn fuzzy_filter<'a>(names: &'a [String], query: &str) -> Vec<&'a str> {
+    let mut matches: Vec<(usize, &str)> = names
+        .iter()
+        .filter_map(|name| match_span(name, query).map(|span| (span, name.as_str())))
+        .collect();
+    // Stable, so that names with the same span keep their order
+    matches.sort_by_key(|&(span, _)| span);
+    matches.into_iter().map(|(_, name)| name).collect()
+}
+
+/// Draw the prompt and the matches, and move the cursor back to the prompt.
+fn draw<W: Write>(
+    writer: &mut W,
+    query: &str,
+    matches: &[&str],
+    selected: usize,
+) -> io::Result<()> {
+    let mut screen = format!("\r\x1b[J> {}", query);
+    let shown = matches.len().min(MAX_MATCHES);
+    for (i, name) in matches.iter().take(shown).enumerate() {
+        if i == selected {
+            screen.push_str(&format!("\n\x1b[7m> {}\x1b[0m", name));
+        } else {
+            screen.push_str(&format!("\n  {}", name));
+        }
+    }
+    if shown > 0 {
+        screen.push_str(&format!("\x1b[{}A", shown));
+    }
+    screen.push_str(&format!("\r\x1b[{}C", 2 + query.width()));
+    writer.write_all(screen.as_bytes())?;
+    writer.flush()
+}
+
+/// Let the user pick one of `names` in the terminal.
+///
+/// Typing filters the names, the arrow keys (or Ctrl-P and Ctrl-N) move the
+/// selection and Enter picks the selected name. Return `None` if the user
+/// cancelled with Ctrl-C or Esc.
+pub fn pick(names: &[String]) -> io::Result<Option<String>> {
+    let mut tty = OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+    let _raw_mode = RawMode::enable(&tty)?;
+
+    let mut query = String::new();
+    let mut selected = 0;
+    let picked = loop {
+        let matches = fuzzy_filter(names, &query);
+        draw(&mut tty, &query, &matches, selected)?;
+        match read_key(&mut tty)? {
+            Key::Char(c) => {
+                query.push(c);
+                selected = 0;
+            }
+            Key::Backspace => {
+                query.pop();
+                selected = 0;
+            }
+            Key::Up => selected = selected.saturating_sub(1),
+            Key::Down if selected + 1 < matches.len().min(MAX_MATCHES) => selected += 1,
+            Key::Enter if !matches.is_empty() => break Some(matches[selected].to_string()),
+            Key::Cancel => break None,
+            Key::Down | Key::Enter | Key::Other => {}
+        }
+    };
+
+    // Remove the picker from the screen
+    tty.write_all(b"\r\x1b[J")?;
+    tty.flush()?;
+    Ok(picked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fuzzy_filter, match_span, read_key, Key, KeyInput};
+    use std::io;
+
+    impl KeyInput for &[u8] {
+        fn input_pending(&mut self) -> io::Result<bool> {
+            Ok(!self.is_empty())
+        }
+    }
+
+    #[test]
+    fn test_fuzzy_filter() {
+        assert_eq!(match_span("git-commit", "gc"), Some(4));
+        assert_eq!(match_span("git-commit", "GIT"), Some(2));
+        assert_eq!(match_span("git-commit", ""), Some(0));
+        assert_eq!(match_span("git-commit", "cg"), None);
+
+        let names: Vec<String> = vec!["gcc", "git", "git-commit", "tar"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        assert_eq!(
+            fuzzy_filter(&names, ""),
+            vec!["gcc", "git", "git-commit", "tar"]
+        );
+        assert_eq!(fuzzy_filter(&names, "gc"), vec!["gcc", "git-commit"]);
+        assert_eq!(fuzzy_filter(&names, "gi"), vec!["git", "git-commit"]);
+        assert!(fuzzy_filter(&names, "xyz").is_empty());
+    }
+
+    #[test]
+    fn test_read_key() {
+        let mut input: &[u8] = b"a\x1b[A\x1b[B\x7f\r\x03\xc3\xa4";
+        let keys: Vec<Key> = (0..7).map(|_| read_key(&mut input).unwrap()).collect();
+        assert_eq!(
+            keys,
+            vec![
+                Key::Char('a'),
+                Key::Up,
+                Key::Down,
+                Key::Backspace,
+                Key::Enter,
+                Key::Cancel,
+                Key::Char('\u{e4}'),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_key_escape() {
+        // Without the rest of an escape sequence, it was Esc
+        let mut input: &[u8] = b"\x1b";
+        assert_eq!(read_key(&mut input).unwrap(), Key::Cancel);
+
+        let mut incomplete: &[u8] = b"\x1b[";
+        assert_eq!(read_key(&mut incomplete).unwrap(), Key::Other);
+        assert!(incomplete.is_empty());
+    }
+}
@@ -0,0 +1,188 @@
+//! The JSON representation of pages (and errors), used with `--format json`.
+
+use std::io::Write;
+
+use serde_derive::Serialize;
+
+use crate::error::TealdeerError::{self, WriteError};
+use crate::types::LineType;
+
+/// A page, as written with `--format json`.
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct JsonPage {
+    /// The name of the command
+    pub name: String,
+    /// The description lines, separated by newlines
+    pub description: String,
+    /// The URL of the "More information" line of the description, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub more_info_url: Option<String>,
+    /// The commands of the "See also" line of the description, if any
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub see_also: Vec<String>,
+    /// The synopsis of the "Usage" line of the description, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub synopsis: Option<String>,
+    pub examples: Vec<JsonExample>,
+}
+
+/// An example of a page, as written with `--format json`.
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct JsonExample {
+    pub description: String,
+    /// The example code, with placeholders (e.g. `{{file}}`). Examples with
+    /// multiple lines of code are separated by newlines.
+    pub command: String,
+    /// The language of the code, from a `<!-- language: <name> -->` hint
+    /// (`shell` without a hint)
+    pub language: String,
+}
+
+/// The language of example code without a language hint.
+pub const DEFAULT_CODE_LANGUAGE: &str = "shell";
+
+/// An error, as written to stdout with `--format json` (in addition to the
+/// message on stderr), so that scripts can parse both outcomes.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct JsonError<'a> {
+    /// The kind of error, `not_found` or `cache_missing`
+    pub error: &'a str,
+    /// The requested command, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<&'a str>,
+}
+
+impl JsonError<'_> {
+    /// Write the error as a single line of JSON.
+    pub fn write<T: Write>(&self, writer: &mut T) -> Result<(), TealdeerError> {
+        serde_json::to_writer(&mut *writer, self).map_err(|e| WriteError(e.to_string()))?;
+        writeln!(writer).map_err(|e| WriteError(e.to_string()))
+    }
+}
+
+/// Append `line` to `text`, separated by a newline.
+fn push_line(text: &mut String, line: &str) {
+    if !text.is_empty() {
+        text.push('\n');
+    }
+    text.push_str(line);
+}
+
+impl JsonPage {
+    /// Collect the page from the tokens of a page (and its patch).
+    pub fn from_tokens<I>(tokens: I) -> Self
+    where
+        I: IntoIterator<Item = LineType>,
+    {
+        let mut page = Self::default();
+        // The hint applies to the code that follows it
+        let mut language = None;
+        for token in tokens {
+            match token {
+                LineType::Title(title) if page.name.is_empty() => page.name = title,
+                LineType::Description(text) => push_line(&mut page.description, &text),
+                LineType::MoreInfo(url) => page.more_info_url = Some(url),
+                LineType::SeeAlso(commands) => page.see_also.extend(commands),
+                LineType::Synopsis(synopsis) => page.synopsis = Some(synopsis),
+                LineType::ExampleText(text) => page.examples.push(JsonExample {
+                    description: text,
+                    command: String::new(),
+                    language: DEFAULT_CODE_LANGUAGE.into(),
+                }),
+                LineType::CodeLanguage(name) => language = Some(name),
+                LineType::ExampleCode(code) => match page.examples.last_mut() {
+                    Some(example) => {
+                        if example.command.is_empty() {
+                            if let Some(language) = language.take() {
+                                example.language = language;
+                            }
+                        }
+                        push_line(&mut example.command, &code);
+                    }
+                    None => page.examples.push(JsonExample {
+                        description: String::new(),
+                        command: code,
+                        language: language
+                            .take()
+                            .unwrap_or_else(|| DEFAULT_CODE_LANGUAGE.into()),
+                    }),
+                },
+                LineType::Title(_) | LineType::Empty | LineType::Other(_) => {}
+            }
+        }
+        page
+    }
+
+    /// Write the page as pretty-printed JSON, followed by a newline.
+    pub fn write<T: Write>(&self, writer: &mut T) -> Result<(), TealdeerError> {
+        serde_json::to_writer_pretty(&mut *writer, self).map_err(|e| WriteError(e.to_string()))?;
+        writeln!(writer).map_err(|e| WriteError(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{JsonExample, JsonPage};
+    use crate::types::LineType;
+
+    #[test]
+    fn test_multiline() {
+        let page = JsonPage::from_tokens(vec![
+            LineType::Title("tar".into()),
+            LineType::Description("Archiving utility.".into()),
+            LineType::Description("Often combined with gzip.".into()),
+            LineType::MoreInfo("https://example.com/tar".into()),
+            LineType::SeeAlso(vec!["zip".into(), "gzip".into()]),
+            LineType::Synopsis("tar [options] {{file}}".into()),
+            LineType::ExampleText("Create an archive:".into()),
+            LineType::ExampleCode("tar cf {{target.tar}} \\".into()),
+            LineType::ExampleCode("  {{file1 file2}}".into()),
+        ]);
+        assert_eq!(
+            page,
+            JsonPage {
+                name: "tar".into(),
+                description: "Archiving utility.\nOften combined with gzip.".into(),
+                more_info_url: Some("https://example.com/tar".into()),
+                see_also: vec!["zip".into(), "gzip".into()],
+                synopsis: Some("tar [options] {{file}}".into()),
+                examples: vec![JsonExample {
+                    description: "Create an archive:".into(),
+                    command: "tar cf {{target.tar}} \\\n  {{file1 file2}}".into(),
+                    language: "shell".into(),
+                }],
+            }
+        );
+
+        // Newlines are escaped, not dropped
+        let mut output = Vec::new();
+        page.write(&mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(
+            output.contains(r#""command": "tar cf {{target.tar}} \\\n  {{file1 file2}}""#),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains(r#""description": "Archiving utility.\nOften combined with gzip.""#)
+        );
+    }
+
+    #[test]
+    fn test_code_language() {
+        let page = JsonPage::from_tokens(vec![
+            LineType::Title("dir".into()),
+            LineType::ExampleText("List the files:".into()),
+            LineType::CodeLanguage("powershell".into()),
+            LineType::ExampleCode("Get-ChildItem".into()),
+            LineType::ExampleText("List the files in cmd:".into()),
+            LineType::ExampleCode("dir".into()),
+        ]);
+        let languages: Vec<&str> = page
+            .examples
+            .iter()
+            .map(|example| example.language.as_str())
+            .collect();
+        assert_eq!(languages, vec!["powershell", "shell"]);
+    }
+}
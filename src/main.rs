@@ -14,37 +14,66 @@
 #![allow(clippy::module_name_repetitions)]
 #![allow(clippy::too_many_lines)]
 
-use std::fs::File;
-use std::io::BufRead;
-use std::io::BufReader;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter};
 use std::iter;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process;
+use std::time::Duration;
 use std::{env, io::Write};
 
 use ansi_term::{Color, Style};
 use app_dirs::AppInfo;
 use atty::Stream;
 use docopt::Docopt;
+use log::{debug, LevelFilter};
 #[cfg(not(target_os = "windows"))]
 use pager::Pager;
 use serde_derive::Deserialize;
+use terminal_size::{terminal_size, Width};
 
+#[cfg(feature = "benchmark")]
+mod benchmark;
 mod cache;
 mod config;
 mod dedup;
+mod diff;
 mod error;
 mod formatter;
+#[cfg(all(feature = "interactive", unix))]
+mod interactive;
+mod json;
+mod man;
+mod paths;
+mod script;
 mod tokenizer;
 mod types;
+#[cfg(feature = "watch")]
+mod watch;
 
-use crate::cache::{Cache, PageLookupResult};
-use crate::config::{get_config_dir, get_config_path, make_default_config, Config, MAX_CACHE_AGE};
+use crate::cache::{Cache, IndexEntry, PageListEntry, PageLookupResult};
+use crate::config::{
+    get_config_dir, get_config_path, make_default_config, Config, StyleConfig, Theme, MAX_CACHE_AGE,
+};
 use crate::dedup::Dedup;
-use crate::error::TealdeerError::ConfigError;
-use crate::formatter::print_lines;
+use crate::error::{
+    ExitCode,
+    TealdeerError::{ConfigError, WriteError},
+};
+use crate::formatter::{
+    first_description, first_example, page_tokens, print_lines, print_lines_unstyled,
+    remove_description, rendered_example_count, truncate, THEME_PREVIEW_PAGE,
+};
+use crate::json::{JsonError, JsonPage};
+use crate::man::ManSummary;
+use crate::script::write_script;
 use crate::tokenizer::Tokenizer;
-use crate::types::{ColorOptions, OsType};
+use crate::types::{
+    ColorOptions, LineType, ListOrder, OsType, OutputFormat, PlaceholderStyle, SYNOPSIS_PREFIX,
+};
 
 const NAME: &str = "tealdeer";
 const APP_INFO: AppInfo = AppInfo {
@@ -53,9 +82,12 @@ const APP_INFO: AppInfo = AppInfo {
 };
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 const USAGE: &str = include_str!("usage.docopt");
-const ARCHIVE_URL: &str = "https://github.com/tldr-pages/tldr/archive/master.tar.gz";
 #[cfg(not(target_os = "windows"))]
 const PAGER_COMMAND: &str = "less -R";
+/// Width of the default separator between pages with `--manifest`
+const PAGE_SEPARATOR_WIDTH: usize = 40;
+/// Box drawing character of the default separator between pages
+const RULE_CHAR: char = '\u{2500}';
 
 #[allow(clippy::struct_excessive_bools)]
 #[derive(Debug, Deserialize)]
@@ -64,54 +96,921 @@ struct Args {
     flag_help: bool,
     flag_version: bool,
     flag_list: bool,
+    flag_interactive: bool,
+    flag_long: bool,
+    flag_count: bool,
+    flag_null: bool,
+    flag_dump_index: bool,
+    flag_all_platforms: bool,
+    flag_watch: bool,
+    flag_group_by_platform: bool,
+    flag_sort: ListOrder,
+    flag_custom_only: bool,
+    flag_limit: Option<usize>,
+    flag_offset: usize,
     flag_render: Option<String>,
+    flag_output: Option<PathBuf>,
+    flag_max_lines: Option<usize>,
+    flag_diff: Option<String>,
+    flag_page_url: Option<String>,
+    flag_explain: Option<String>,
+    flag_which: Option<String>,
+    flag_prefetch: Option<PathBuf>,
+    flag_manifest: Option<PathBuf>,
+    flag_export: Option<PathBuf>,
+    flag_with_custom_pages: bool,
     flag_os: Option<OsType>,
+    flag_platform_list: bool,
+    flag_language_list: bool,
+    flag_no_common: bool,
     flag_update: bool,
+    flag_check_update: bool,
     flag_clear_cache: bool,
     flag_pager: bool,
     flag_quiet: bool,
     flag_show_paths: bool,
+    flag_config: Option<PathBuf>,
+    flag_config_dir: Option<PathBuf>,
+    flag_strict_config: bool,
     flag_config_path: bool,
     flag_seed_config: bool,
+    flag_force: bool,
+    flag_minimal: bool,
     flag_markdown: bool,
+    flag_format: OutputFormat,
+    flag_oneline: bool,
+    flag_hexdump: bool,
+    flag_as_script: bool,
+    flag_examples_only: bool,
+    flag_line_numbers: bool,
+    flag_grep: Option<String>,
+    flag_dedup: bool,
+    flag_set: Vec<String>,
+    flag_placeholders: PlaceholderStyle,
+    flag_no_styling: bool,
     flag_color: ColorOptions,
+    flag_theme: Option<Theme>,
+    flag_theme_preview: bool,
+    flag_benchmark: Option<usize>,
     flag_language: Option<String>,
+    flag_verbose: usize,
 }
 
 /// Print page by path
 fn print_page(
     page: &PageLookupResult,
+    mut writer: &mut dyn Write,
+    format: OutputFormat,
     enable_markdown: bool,
+    enable_styling: bool,
+    width: Option<usize>,
     config: &Config,
 ) -> Result<(), String> {
-    let stdout = std::io::stdout();
-    let mut handle = stdout.lock();
+    if format == OutputFormat::Json {
+        return print_page_json(page, writer, config);
+    }
+
+    let header = config.display.header.as_deref();
+    if let (Some(template), false) = (header, enable_markdown) {
+        writeln!(
+            writer,
+            "\n{}",
+            page_frame(template, page, enable_styling, config)
+        )
+        .map_err(|_| "Could not write output".to_string())?;
+    }
 
     for path in page.paths() {
+        debug!("Reading page file {}", path.display());
         let file = File::open(path).map_err(|msg| format!("Could not open file: {}", msg))?;
         let reader = BufReader::new(file);
 
         if enable_markdown {
             // Print the raw markdown of the file.
-            for line in reader.lines() {
-                writeln!(handle, "{}", line.unwrap())
-                    .map_err(|_| "Could not write to stdout".to_string())?;
+            let mut lossy = false;
+            for line in reader.split(b'\n') {
+                let line = line.map_err(|msg| format!("Could not read file: {}", msg))?;
+                let line = String::from_utf8_lossy(&line);
+                lossy |= matches!(line, Cow::Owned(_));
+                writeln!(writer, "{}", line.trim_end_matches('\r'))
+                    .map_err(|_| "Could not write output".to_string())?;
+            }
+            if lossy {
+                warn_invalid_utf8(path);
             }
         } else {
             // Create tokenizer and print output
-            let mut tokenizer = Tokenizer::new(reader);
-            print_lines(&mut handle, &mut tokenizer, &config)
-                .map_err(|e| format!("Could not write to stdout: {}", e.message()))?;
+            let mut tokenizer =
+                Tokenizer::new(reader).with_trimmed_code(config.display.trim_example_whitespace);
+            if enable_styling {
+                print_lines(&mut writer, &mut tokenizer, page.platform(), width, &config)
+            } else {
+                print_lines_unstyled(&mut writer, &mut tokenizer, width, &config)
+            }
+            .map_err(|e| format!("Could not write output: {}", e.message()))?;
+            if tokenizer.is_lossy() {
+                warn_invalid_utf8(path);
+            }
         };
     }
 
-    handle
+    let footer = config.display.footer.as_deref();
+    if let (Some(template), false) = (footer, enable_markdown) {
+        writeln!(
+            writer,
+            "{}\n",
+            page_frame(template, page, enable_styling, config)
+        )
+        .map_err(|_| "Could not write output".to_string())?;
+    }
+
+    writer
         .flush()
-        .map_err(|_| "Could not flush stdout".to_string())?;
+        .map_err(|_| "Could not flush output".to_string())?;
 
     Ok(())
 }
 
+/// Return the `header` or `footer` of the config for `page`, with `{name}`
+/// and `{platform}` expanded and every line indented like the description.
+fn page_frame(
+    template: &str,
+    page: &PageLookupResult,
+    enable_styling: bool,
+    config: &Config,
+) -> String {
+    let name = page
+        .paths()
+        .next()
+        .and_then(Path::file_stem)
+        .map_or(Cow::Borrowed(""), OsStr::to_string_lossy);
+    let text = template
+        .replace("{name}", &name)
+        .replace("{platform}", page.platform().map_or("common", OsType::name));
+    let style = if enable_styling && config.display.dim_header_footer {
+        Style::new().dimmed()
+    } else {
+        Style::new()
+    };
+    text.lines()
+        .map(|line| {
+            format!(
+                "{}{}",
+                " ".repeat(config.display.description_indent),
+                style.paint(line)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Print a page (and its patch) as a single JSON object.
+fn print_page_json(
+    page: &PageLookupResult,
+    mut writer: &mut dyn Write,
+    config: &Config,
+) -> Result<(), String> {
+    let mut tokens = Vec::new();
+    for path in page.paths() {
+        debug!("Reading page file {}", path.display());
+        let file = File::open(path).map_err(|msg| format!("Could not open file: {}", msg))?;
+        let mut tokenizer = Tokenizer::new(BufReader::new(file))
+            .with_trimmed_code(config.display.trim_example_whitespace);
+        tokens.extend(page_tokens(&mut tokenizer, config));
+        if tokenizer.is_lossy() {
+            warn_invalid_utf8(path);
+        }
+    }
+
+    JsonPage::from_tokens(tokens)
+        .write(&mut writer)
+        .and_then(|()| writer.flush().map_err(|e| WriteError(e.to_string())))
+        .map_err(|e| format!("Could not write output: {}", e.message()))
+}
+
+/// Print the language and platform the page was found for as a dimmed
+/// caption, e.g. "(en, common)".
+fn print_resolution_caption(
+    page: &PageLookupResult,
+    writer: &mut dyn Write,
+    config: &Config,
+) -> Result<(), String> {
+    let caption = match page.language() {
+        Some(language) => format!(
+            "({}, {})",
+            language,
+            page.platform().map_or("common", OsType::name)
+        ),
+        None => "(custom)".to_string(),
+    };
+    writeln!(
+        writer,
+        "{}{}",
+        " ".repeat(config.display.description_indent),
+        Style::new().dimmed().paint(caption)
+    )
+    .and_then(|()| writer.flush())
+    .map_err(|e| format!("Could not write output: {}", e))
+}
+
+/// Print the NAME and SYNOPSIS of the man page of a command above its page
+/// (`--explain`).
+fn print_man_summary(
+    summary: &ManSummary,
+    writer: &mut dyn Write,
+    enable_styling: bool,
+    config: &Config,
+) -> Result<(), String> {
+    let (description, synopsis) = if enable_styling {
+        (config.style.description, config.style.synopsis)
+    } else {
+        (Style::new(), Style::new())
+    };
+    let indent = " ".repeat(config.display.description_indent);
+    let mut lines = Vec::new();
+    if let Some(ref name) = summary.name {
+        lines.push(format!("{}{}", indent, description.paint(name)));
+    }
+    for (i, line) in summary.synopsis.iter().enumerate() {
+        // The forms of the command are aligned below the first one
+        let prefix = if i == 0 {
+            format!("{} ", SYNOPSIS_PREFIX)
+        } else {
+            " ".repeat(SYNOPSIS_PREFIX.len() + 1)
+        };
+        lines.push(format!(
+            "{}{}{}",
+            indent,
+            description.paint(prefix),
+            synopsis.paint(line)
+        ));
+    }
+    lines
+        .iter()
+        .try_for_each(|line| writeln!(writer, "{}", line))
+        .and_then(|()| writer.flush())
+        .map_err(|e| format!("Could not write output: {}", e))
+}
+
+/// Return the number of examples of a page (and its patch) that are rendered,
+/// see `rendered_example_count`.
+fn count_rendered_examples(page: &PageLookupResult, config: &Config) -> Result<usize, String> {
+    let mut count = 0;
+    for path in page.paths() {
+        let file = File::open(path).map_err(|msg| format!("Could not open file: {}", msg))?;
+        count += rendered_example_count(&mut Tokenizer::new(BufReader::new(file)), config);
+    }
+    Ok(count)
+}
+
+/// Return the web URLs of the pages of the commands on the "See also" line of
+/// a page (and its patch), see `get_page_url`. Commands without a page are
+/// omitted.
+fn see_also_urls(
+    cache: &Cache,
+    page: &PageLookupResult,
+    languages: &[String],
+    config: &Config,
+) -> HashMap<String, String> {
+    let mut urls = HashMap::new();
+    for path in page.paths() {
+        // Errors are reported when rendering the page
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(_) => continue,
+        };
+        let mut tokenizer = Tokenizer::new(BufReader::new(file));
+        while let Some(token) = tokenizer.next_token() {
+            if let LineType::SeeAlso(commands) = token {
+                for command in commands {
+                    if let Ok(url) = get_page_url(cache, &command, languages, config) {
+                        urls.insert(command, url);
+                    }
+                }
+            }
+        }
+    }
+    urls
+}
+
+/// Print the examples of a page (and its patch) as a shell script.
+fn print_page_script(
+    page: &PageLookupResult,
+    mut writer: &mut dyn Write,
+    config: &Config,
+) -> Result<(), String> {
+    let mut tokens = Vec::new();
+    for path in page.paths() {
+        debug!("Reading page file {}", path.display());
+        let file = File::open(path).map_err(|msg| format!("Could not open file: {}", msg))?;
+        let mut tokenizer = Tokenizer::new(BufReader::new(file))
+            .with_trimmed_code(config.display.trim_example_whitespace);
+        tokens.extend(page_tokens(&mut tokenizer, config));
+        if tokenizer.is_lossy() {
+            warn_invalid_utf8(path);
+        }
+    }
+    if config.display.examples_only {
+        tokens = remove_description(tokens);
+    }
+
+    write_script(tokens, &mut writer)
+        .map_err(|e| format!("Could not write output: {}", e.message()))
+}
+
+/// Print the built-in preview page with the styles of the current config.
+fn print_theme_preview(
+    mut writer: &mut dyn Write,
+    enable_styling: bool,
+    width: Option<usize>,
+    config: &Config,
+) -> Result<(), String> {
+    let mut tokenizer = Tokenizer::new(THEME_PREVIEW_PAGE.as_bytes());
+    if enable_styling {
+        print_lines(&mut writer, &mut tokenizer, None, width, config)
+    } else {
+        print_lines_unstyled(&mut writer, &mut tokenizer, width, config)
+    }
+    .and_then(|()| writer.flush().map_err(|e| WriteError(e.to_string())))
+    .map_err(|e| format!("Could not write output: {}", e.message()))
+}
+
+/// Warn that the page file at `path` contains invalid UTF-8.
+fn warn_invalid_utf8(path: &Path) {
+    eprintln!(
+        "Warning: {} is not valid UTF-8, invalid bytes were replaced",
+        path.display()
+    );
+}
+
+/// Print the first example of a page without styling and without a trailing
+/// newline. Return whether the page has an example.
+fn print_first_example(
+    page: &PageLookupResult,
+    writer: &mut dyn Write,
+    config: &Config,
+) -> Result<bool, String> {
+    for path in page.paths() {
+        let file = File::open(path).map_err(|msg| format!("Could not open file: {}", msg))?;
+        let mut tokenizer = Tokenizer::new(BufReader::new(file))
+            .with_trimmed_code(config.display.trim_example_whitespace);
+        if let Some(example) = first_example(&mut tokenizer) {
+            write!(writer, "{}", example)
+                .and_then(|_| writer.flush())
+                .map_err(|_| "Could not write output".to_string())?;
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Return the lines of a hex and ASCII dump of `bytes` (like `hexdump -C`),
+/// 16 bytes per line. Bytes that are not printable ASCII are shown as `.`.
+fn hexdump_lines(bytes: &[u8]) -> Vec<String> {
+    let mut lines: Vec<String> = bytes
+        .chunks(16)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let mut hex = String::new();
+            for (j, byte) in chunk.iter().enumerate() {
+                if j == 8 {
+                    hex.push(' ');
+                }
+                hex.push_str(&format!("{:02x} ", byte));
+            }
+            let ascii: String = chunk
+                .iter()
+                .map(|&byte| match byte {
+                    b' '..=b'~' => char::from(byte),
+                    _ => '.',
+                })
+                .collect();
+            format!("{:08x}  {:<49} |{}|", i * 16, hex, ascii)
+        })
+        .collect();
+    lines.push(format!("{:08x}", bytes.len()));
+    lines
+}
+
+/// Print a hex dump of the page files, to find stray bytes (e.g. a BOM or
+/// carriage returns) that are not visible in the rendered page.
+fn print_hexdump(page: &PageLookupResult, writer: &mut dyn Write) -> Result<(), String> {
+    let paths: Vec<&Path> = page.paths().collect();
+    for (i, path) in paths.iter().enumerate() {
+        let bytes = fs::read(path).map_err(|msg| format!("Could not open file: {}", msg))?;
+        let write_err = |_| "Could not write output".to_string();
+        if paths.len() > 1 {
+            if i > 0 {
+                writeln!(writer).map_err(write_err)?;
+            }
+            writeln!(writer, "{}:", path.display()).map_err(write_err)?;
+        }
+        for line in hexdump_lines(&bytes) {
+            writeln!(writer, "{}", line).map_err(write_err)?;
+        }
+    }
+    writer
+        .flush()
+        .map_err(|_| "Could not flush output".to_string())
+}
+
+/// Return the width of the terminal that pages are rendered to, if any.
+///
+/// The `COLUMNS` env variable takes precedence over the terminal width. Wider
+/// terminals are capped at the `max_width` of the config.
+fn get_output_width(args: &Args, config: &Config) -> Option<usize> {
+    if args.flag_output.is_some() {
+        return None;
+    }
+    env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+        .or_else(|| terminal_size().map(|(Width(width), _)| usize::from(width)))
+        .map(|width: usize| config.display.max_width.map_or(width, |max| width.min(max)))
+}
+
+/// Return the number of lines the output is cut off after (`--max-lines`),
+/// JSON output is never cut off.
+fn get_max_lines(args: &Args) -> usize {
+    args.flag_max_lines
+        .filter(|_| args.flag_format == OutputFormat::Text)
+        .unwrap_or(usize::MAX)
+}
+
+/// Open the output for rendered pages.
+///
+/// This is the file passed through `--output` if set, stdout otherwise.
+/// Existing files are overwritten.
+fn open_output(output_path: Option<&Path>) -> Result<Box<dyn Write>, String> {
+    match output_path {
+        Some(path) if path.is_dir() => Err(format!(
+            "Could not write output: {} is a directory",
+            path.display()
+        )),
+        Some(path) => {
+            let file = File::create(path)
+                .map_err(|msg| format!("Could not create {}: {}", path.display(), msg))?;
+            Ok(Box::new(BufWriter::new(file)))
+        }
+        None => Ok(Box::new(io::stdout())),
+    }
+}
+
+/// Writes at most `max_lines` lines of the output to `inner` and counts the
+/// lines after them, see `--max-lines`.
+struct LineLimit<W: Write> {
+    inner: W,
+    max_lines: usize,
+    lines: usize,
+    hidden: usize,
+}
+
+impl<W: Write> LineLimit<W> {
+    fn new(inner: W, max_lines: usize) -> Self {
+        Self {
+            inner,
+            max_lines,
+            lines: 0,
+            hidden: 0,
+        }
+    }
+
+    /// Write a note of how many lines were cut off (unless `quietly`).
+    fn finish(mut self, quietly: bool) -> io::Result<()> {
+        if self.hidden > 0 && !quietly {
+            let noun = if self.hidden == 1 { "line" } else { "lines" };
+            writeln!(
+                self.inner,
+                "\u{2026} (truncated, {} more {})",
+                self.hidden, noun
+            )?;
+        }
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Write for LineLimit<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut end = 0;
+        while end < buf.len() && self.lines < self.max_lines {
+            if buf[end] == b'\n' {
+                self.lines += 1;
+            }
+            end += 1;
+        }
+        self.inner.write_all(&buf[..end])?;
+        #[allow(clippy::naive_bytecount)]
+        let hidden = buf[end..].iter().filter(|&&byte| byte == b'\n').count();
+        self.hidden += hidden;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Print a unified diff between the upstream page and the custom page of a command.
+///
+/// Return `false` if the command has neither page.
+fn print_custom_page_diff(
+    cache: &Cache,
+    command: &str,
+    languages: &[String],
+    config: &Config,
+) -> Result<bool, String> {
+    let custom_page =
+        Cache::find_custom_page(command, config.directories.custom_pages_dir.as_deref());
+    let upstream_page = cache.find_upstream_page(command, languages);
+
+    let (upstream_page, custom_page) = match (upstream_page, custom_page) {
+        (Some(upstream_page), Some(custom_page)) => (upstream_page, custom_page),
+        (Some(upstream_page), None) => {
+            return Err(format!(
+                "Only an upstream page exists for {}: {}",
+                command,
+                upstream_page.display()
+            ))
+        }
+        (None, Some(custom_page)) => {
+            return Err(format!(
+                "Only a custom page exists for {}: {}",
+                command,
+                custom_page.display()
+            ))
+        }
+        (None, None) => {
+            eprintln!("Page {} not found", command);
+            return Ok(false);
+        }
+    };
+
+    let read = |path: &Path| {
+        fs::read(path)
+            .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+            .map_err(|msg| format!("Could not read {}: {}", path.display(), msg))
+    };
+    match diff::unified_diff(
+        &read(&upstream_page)?,
+        &read(&custom_page)?,
+        &upstream_page.to_string_lossy(),
+        &custom_page.to_string_lossy(),
+    ) {
+        Some(diff) => print!("{}", diff),
+        None => eprintln!(
+            "The custom page for {} is identical to the upstream page.",
+            command
+        ),
+    }
+    Ok(true)
+}
+
+/// Look up the page of a command that consists of one or more words.
+///
+/// The words are joined with hyphens to find subcommand pages (`git commit`
+/// is looked up as `git-commit`). If there is no such page, trailing words are
+/// dropped until a page is found (`git`).
+fn find_command_page(
+    cache: &Cache,
+    words: &[String],
+    languages: &[String],
+    config: &Config,
+) -> Option<PageLookupResult> {
+    (1..=words.len()).rev().find_map(|len| {
+        let name = words[..len].join("-");
+        debug!("Looking up page {}", name);
+        cache.find_page(
+            &name,
+            languages,
+            config.directories.custom_pages_dir.as_deref(),
+        )
+    })
+}
+
+/// Return the name of a page in the list of pages, with a `[custom]` marker if
+/// `mark_custom` is set and it is a custom page.
+fn list_entry_label(page: &PageListEntry, mark_custom: bool) -> Cow<str> {
+    if mark_custom && page.custom {
+        Cow::Owned(format!("{} [custom]", page.name))
+    } else {
+        Cow::Borrowed(&page.name)
+    }
+}
+
+/// Print the pages, followed by their descriptions.
+///
+/// With a known `width`, the lines are truncated to fit into the terminal.
+fn print_long_list(
+    cache: &Cache,
+    pages: &[PageListEntry],
+    mark_custom: bool,
+    languages: &[String],
+    width: Option<usize>,
+    config: &Config,
+) {
+    let labels: Vec<Cow<str>> = pages
+        .iter()
+        .map(|page| list_entry_label(page, mark_custom))
+        .collect();
+    let label_width = labels.iter().map(|label| label.len()).max().unwrap_or(0);
+    for (page, label) in pages.iter().zip(&labels) {
+        let description = cache
+            .find_page(
+                &page.name,
+                languages,
+                config.directories.custom_pages_dir.as_deref(),
+            )
+            .and_then(|page| File::open(page.paths().next()?).ok())
+            .and_then(|file| first_description(&mut Tokenizer::new(BufReader::new(file))))
+            .unwrap_or_default();
+        let line = format!("{:width$}  {}", label, description, width = label_width);
+        match width {
+            Some(width) => println!("{}", truncate(line.trim_end(), width)),
+            None => println!("{}", line.trim_end()),
+        }
+    }
+}
+
+/// Write the page index as tab separated values (the command, its platforms
+/// and its languages, comma separated) or as compact JSON.
+fn print_index(
+    index: &[IndexEntry],
+    format: OutputFormat,
+    writer: &mut dyn Write,
+) -> Result<(), String> {
+    let join = |names: &BTreeSet<String>| {
+        names
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+    match format {
+        OutputFormat::Json => serde_json::to_writer(&mut *writer, index)
+            .map_err(io::Error::from)
+            .and_then(|()| writeln!(writer)),
+        OutputFormat::Text => index.iter().try_for_each(|entry| {
+            writeln!(
+                writer,
+                "{}\t{}\t{}",
+                entry.name,
+                join(&entry.platforms),
+                join(&entry.languages)
+            )
+        }),
+    }
+    .and_then(|()| writer.flush())
+    .map_err(|e| format!("Could not write output: {}", e))
+}
+
+/// Print the pages of each platform, see `Cache::list_pages_by_platform`.
+///
+/// The text output has a `## <platform>` header above the pages of each
+/// platform, the JSON output is an object of platforms and page names.
+fn print_grouped_list(
+    groups: &[(String, Vec<String>)],
+    format: OutputFormat,
+    writer: &mut dyn Write,
+) -> Result<(), String> {
+    match format {
+        OutputFormat::Json => {
+            let groups: BTreeMap<&str, &Vec<String>> = groups
+                .iter()
+                .map(|(platform, pages)| (platform.as_str(), pages))
+                .collect();
+            serde_json::to_writer(&mut *writer, &groups)
+                .map_err(io::Error::from)
+                .and_then(|()| writeln!(writer))
+        }
+        OutputFormat::Text => groups
+            .iter()
+            .enumerate()
+            .try_for_each(|(i, (platform, pages))| {
+                if i > 0 {
+                    writeln!(writer)?;
+                }
+                writeln!(writer, "## {}", platform)?;
+                pages
+                    .iter()
+                    .try_for_each(|page| writeln!(writer, "{}", page))
+            }),
+    }
+    .and_then(|()| writer.flush())
+    .map_err(|e| format!("Could not write output: {}", e))
+}
+
+/// Return the upstream web URL of the page of a command.
+///
+/// The platform is resolved from the cache, no network access is needed.
+fn get_page_url(
+    cache: &Cache,
+    command: &str,
+    languages: &[String],
+    config: &Config,
+) -> Result<String, String> {
+    let (name, platform) = cache
+        .find_upstream_page_location(command, languages)
+        .ok_or_else(|| format!("Page {} not found in cache", command))?;
+    Ok(format!(
+        "{}/{}/{}",
+        config.display.page_url_base.trim_end_matches('/'),
+        platform,
+        name
+    ))
+}
+
+/// Check that all pages listed in `list_path` (one command per line) can be
+/// found, and return the number of missing pages.
+fn check_prefetch_list(
+    cache: &Cache,
+    list_path: &Path,
+    languages: &[String],
+    quietly: bool,
+    config: &Config,
+) -> Result<usize, String> {
+    let list = fs::read_to_string(list_path)
+        .map_err(|msg| format!("Could not read {}: {}", list_path.display(), msg))?;
+
+    let mut total = 0;
+    let mut missing = 0;
+    for line in list.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        // Commands with spaces are looked up like multiple arguments
+        let command = line.split_whitespace().collect::<Vec<_>>().join("-");
+        total += 1;
+
+        let page = cache.find_page(
+            &command,
+            languages,
+            config.directories.custom_pages_dir.as_deref(),
+        );
+        match page {
+            None => eprintln!("Page {} not found in cache", command),
+            Some(page) => match page.paths().find(|path| File::open(path).is_err()) {
+                Some(path) => eprintln!("Page {} could not be read: {}", command, path.display()),
+                None => continue,
+            },
+        }
+        missing += 1;
+    }
+
+    if !quietly {
+        if missing == 0 {
+            eprintln!("All {} pages are available.", total);
+        } else {
+            eprintln!("{} of {} pages are missing.", missing, total);
+        }
+    }
+    Ok(missing)
+}
+
+/// A list of commands in TOML, for `--manifest`.
+#[derive(Debug, Deserialize)]
+struct Manifest {
+    commands: Vec<String>,
+}
+
+/// Read the command names listed in `manifest_path`, either one per line or
+/// as a `commands` array if the file has a `.toml` extension. Commands with
+/// spaces are joined with hyphens (`git commit` is `git-commit`).
+fn read_manifest(manifest_path: &Path) -> Result<Vec<String>, String> {
+    let text = fs::read_to_string(manifest_path)
+        .map_err(|msg| format!("Could not read {}: {}", manifest_path.display(), msg))?;
+
+    let commands = if manifest_path.extension().map_or(false, |ext| ext == "toml") {
+        toml::from_str::<Manifest>(&text)
+            .map_err(|msg| format!("Could not parse {}: {}", manifest_path.display(), msg))?
+            .commands
+    } else {
+        text.lines().map(String::from).collect()
+    };
+    Ok(commands
+        .iter()
+        .map(|command| command.split_whitespace().collect::<Vec<_>>().join("-"))
+        .filter(|command| !command.is_empty())
+        .collect())
+}
+
+/// Return the separator above the page of `name` when rendering several
+/// pages: the `page_separator` of the config with `{name}` expanded, or a
+/// dimmed rule with the name.
+fn page_separator(name: &str, enable_styling: bool, config: &Config) -> String {
+    if let Some(ref template) = config.display.page_separator {
+        return template.replace("{name}", name);
+    }
+    let label = format!("{0}{0} {1} ", RULE_CHAR, name);
+    let width = PAGE_SEPARATOR_WIDTH
+        .saturating_sub(label.chars().count())
+        .max(2);
+    let rule = format!("{}{}", label, RULE_CHAR.to_string().repeat(width));
+    if enable_styling {
+        Style::new().dimmed().paint(rule).to_string()
+    } else {
+        rule
+    }
+}
+
+/// Render the pages of `commands` into a single document, with a table of
+/// contents at the top and a separator above each page. Missing pages are
+/// listed at the end, their number is returned.
+fn print_manifest(
+    cache: &Cache,
+    commands: &[String],
+    languages: &[String],
+    args: &Args,
+    writer: &mut dyn Write,
+    enable_styles: bool,
+    config: &Config,
+) -> Result<usize, String> {
+    let (pages, missing): (Vec<_>, Vec<_>) = commands
+        .iter()
+        .map(|command| {
+            let page = cache.find_page(
+                command,
+                languages,
+                config.directories.custom_pages_dir.as_deref(),
+            );
+            (command, page)
+        })
+        .partition(|(_, page)| page.is_some());
+
+    let write_err = |e: io::Error| format!("Could not write output: {}", e);
+    writeln!(writer, "Contents:\n").map_err(write_err)?;
+    for (i, (command, _)) in pages.iter().enumerate() {
+        writeln!(writer, "  {}. {}", i + 1, command).map_err(write_err)?;
+    }
+
+    for (command, page) in &pages {
+        writeln!(
+            writer,
+            "\n{}",
+            page_separator(command, enable_styles, config)
+        )
+        .map_err(write_err)?;
+        if let Some(page) = page {
+            print_page(
+                page,
+                writer,
+                OutputFormat::Text,
+                args.flag_markdown,
+                !args.flag_no_styling,
+                get_output_width(args, config),
+                config,
+            )?;
+        }
+    }
+
+    if !missing.is_empty() {
+        writeln!(writer, "\nMissing pages:\n").map_err(write_err)?;
+        for (command, _) in &missing {
+            writeln!(writer, "  {}", command).map_err(write_err)?;
+        }
+    }
+    writer.flush().map_err(write_err)?;
+    Ok(missing.len())
+}
+
+/// Return whether the version `latest` (e.g. `1.10.0`) is newer than
+/// `current`. Pre-release suffixes (e.g. `-rc1`) are ignored.
+fn is_newer_version(latest: &str, current: &str) -> bool {
+    let parse = |version: &str| -> Vec<u64> {
+        version
+            .split('-')
+            .next()
+            .unwrap_or("")
+            .split('.')
+            .map(|part| part.parse().unwrap_or(0))
+            .collect()
+    };
+    parse(latest) > parse(current)
+}
+
+/// Check whether a newer tealdeer release is available and exit. Nothing is
+/// installed, and errors (e.g. when offline) are only reported unless
+/// `quietly` is set.
+fn check_for_release(cache: &Cache, quietly: bool) -> ! {
+    match cache.latest_release() {
+        Ok(ref latest) if is_newer_version(latest, VERSION) => {
+            println!(
+                "A newer version of tealdeer is available: {} (installed: {})",
+                latest, VERSION
+            );
+            println!("See https://github.com/dbrgn/tealdeer/releases");
+        }
+        Ok(_) => {
+            if !quietly {
+                println!("tealdeer {} is the latest version.", VERSION);
+            }
+        }
+        Err(e) => {
+            if !quietly {
+                eprintln!("Could not check for a newer release: {}", e.message());
+            }
+            process::exit(e.exit_code() as i32);
+        }
+    }
+    process::exit(ExitCode::Success as i32);
+}
+
 /// Set up display pager
 #[cfg(not(target_os = "windows"))]
 fn configure_pager() {
@@ -123,18 +1022,115 @@ fn configure_pager() {
     eprintln!("Warning: -p / --pager flag not available on Windows!");
 }
 
+/// Run the `on_not_found_command` hook for the missing page `name`, with the
+/// name in the `TEALDEER_COMMAND` environment variable.
+///
+/// The output of the hook is shown to the user. Failures are reported, but
+/// don't change the exit code.
+fn run_not_found_command(command_line: &str, name: &str, quiet: bool) {
+    #[cfg(not(target_os = "windows"))]
+    let mut command = process::Command::new("sh");
+    #[cfg(not(target_os = "windows"))]
+    command.arg("-c");
+    #[cfg(target_os = "windows")]
+    let mut command = process::Command::new("cmd");
+    #[cfg(target_os = "windows")]
+    command.arg("/C");
+
+    debug!("Running on_not_found_command: {}", command_line);
+    let result = command
+        .arg(command_line)
+        .env("TEALDEER_COMMAND", name)
+        .status();
+    if quiet {
+        return;
+    }
+    match result {
+        Ok(status) if status.success() => {}
+        Ok(status) => eprintln!("Warning: on_not_found_command failed ({})", status),
+        Err(e) => eprintln!("Warning: Could not run on_not_found_command: {}", e),
+    }
+}
+
 fn should_update_cache(args: &Args, config: &Config) -> bool {
+    // Without networking or with a read-only cache, only explicit updates
+    // are attempted (and fail)
     args.flag_update
         || (config.updates.auto_update
-            && Cache::last_update().map_or(true, |ago| ago >= config.updates.auto_update_interval))
+            && config.updates.networking
+            && !config.directories.cache_readonly
+            && Cache::last_update().map_or(true, |ago| {
+                ago >= config.updates.auto_update_interval
+                    || config.updates.max_cache_age.map_or(false, |max| ago >= max)
+            }))
+}
+
+/// With `--format json`, write the error `error` (about `command`) to stdout,
+/// see `JsonError`.
+fn print_json_error(args: &Args, error: &str, command: Option<&str>) {
+    if args.flag_format == OutputFormat::Json {
+        if let Err(e) = (JsonError { error, command }).write(&mut io::stdout()) {
+            eprintln!("{}", e.message());
+        }
+    }
+}
+
+/// Ask whether the stale cache should be updated.
+fn confirm_update(ago: Duration) -> bool {
+    eprint!(
+        "Cache is {} days old. Update now? [y/N] ",
+        ago.as_secs() / 24 / 3600
+    );
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).is_ok()
+        && matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
 }
 
 /// Check the cache for freshness
-fn check_cache(args: &Args, enable_styles: bool) {
-    match Cache::last_update() {
+///
+/// If enabled in the config, an interactive user is asked whether a stale
+/// cache should be updated. Returns whether the cache is still stale.
+fn check_cache(args: &Args, cache: &Cache, config: &Config, enable_styles: bool) -> bool {
+    let last_update = Cache::last_update();
+
+    // Don't render pages from a cache with an unknown layout
+    if last_update.is_some() {
+        if let Err(e) = Cache::check_format(config.directories.cache_readonly) {
+            eprintln!("{}", e.message());
+            process::exit(e.exit_code() as i32);
+        }
+    }
+
+    match last_update {
+        Some(ago) if config.updates.max_cache_age.map_or(false, |max| ago > max) => {
+            eprintln!(
+                "The cache hasn't been updated for more than {} hours (the \
+                 `max_cache_age_hours` of the config).\n\
+                 Please run `tldr --update`.",
+                config
+                    .updates
+                    .max_cache_age
+                    .map_or(0, |max| max.as_secs() / 3600)
+            );
+            print_json_error(args, "cache_missing", None);
+            process::exit(ExitCode::CacheMissing as i32);
+        }
         Some(ago) if ago > MAX_CACHE_AGE => {
             if args.flag_quiet {
-                return;
+                return true;
+            }
+
+            if config.updates.prompt_on_stale
+                && config.updates.networking
+                && !config.directories.cache_readonly
+                && atty::is(Stream::Stdout)
+                && atty::is(Stream::Stdin)
+            {
+                if confirm_update(ago) {
+                    update_cache(cache, args.flag_quiet);
+                    return false;
+                }
+                return true;
             }
 
             // Only use color if enabled
@@ -152,20 +1148,22 @@ fn check_cache(args: &Args, enable_styles: bool) {
                     MAX_CACHE_AGE.as_secs() / 24 / 3600
                 ))
             );
+            true
         }
-        Some(_) => {}
+        Some(_) => false,
         None => {
             eprintln!("Cache not found. Please run `tldr --update`.");
-            process::exit(1);
+            print_json_error(args, "cache_missing", None);
+            process::exit(ExitCode::CacheMissing as i32);
         }
-    };
+    }
 }
 
 /// Clear the cache
-fn clear_cache(quietly: bool) {
-    Cache::clear().unwrap_or_else(|e| {
+fn clear_cache(cache: &Cache, quietly: bool) {
+    cache.clear().unwrap_or_else(|e| {
         eprintln!("Could not delete cache: {}", e.message());
-        process::exit(1);
+        process::exit(e.exit_code() as i32);
     });
     if !quietly {
         eprintln!("Successfully deleted cache.");
@@ -176,33 +1174,103 @@ fn clear_cache(quietly: bool) {
 fn update_cache(cache: &Cache, quietly: bool) {
     cache.update().unwrap_or_else(|e| {
         eprintln!("Could not update cache: {}", e.message());
-        process::exit(1);
+        process::exit(e.exit_code() as i32);
     });
     if !quietly {
         eprintln!("Successfully updated cache.");
     }
 }
 
+/// Let the user pick a page in the terminal. Return `None` if the user
+/// cancelled.
+#[cfg(all(feature = "interactive", unix))]
+fn pick_page(cache: &Cache, config: &Config) -> Result<Option<String>, String> {
+    let pages = cache
+        .list_pages(
+            config.directories.custom_pages_dir.as_deref(),
+            false,
+            ListOrder::Name,
+            0,
+            None,
+        )
+        .map_err(|e| format!("Could not get list of pages: {}", e.message()))?;
+    let names: Vec<String> = pages.into_iter().map(|page| page.name).collect();
+    interactive::pick(&names).map_err(|e| format!("Could not read from the terminal: {}", e))
+}
+
+#[cfg(not(all(feature = "interactive", unix)))]
+fn pick_page(_cache: &Cache, _config: &Config) -> Result<Option<String>, String> {
+    Err(
+        "The interactive page picker is not available in this build \
+         (it requires the `interactive` feature and a Unix terminal)"
+            .into(),
+    )
+}
+
+/// Render the built-in benchmark corpus `runs` times and print the results.
+#[cfg(feature = "benchmark")]
+fn run_benchmark(runs: usize, config: &Config) -> Result<(), String> {
+    let report = benchmark::run(runs, config)
+        .map_err(|e| format!("Could not render the benchmark pages: {}", e.message()))?;
+    println!("{}", report);
+    Ok(())
+}
+
+#[cfg(not(feature = "benchmark"))]
+fn run_benchmark(_runs: usize, _config: &Config) -> Result<(), String> {
+    Err("The benchmark is not available in this build (it requires the `benchmark` feature)".into())
+}
+
+/// Call `render` whenever the page file `path` changes, see `watch::watch`.
+#[cfg(feature = "watch")]
+fn watch_page<F>(path: &Path, render: F) -> Result<(), String>
+where
+    F: FnMut() -> Result<(), String>,
+{
+    watch::watch(path, render)
+}
+
+#[cfg(not(feature = "watch"))]
+fn watch_page<F>(_path: &Path, _render: F) -> Result<(), String>
+where
+    F: FnMut() -> Result<(), String>,
+{
+    Err("The live preview is not available in this build (it requires the `watch` feature)".into())
+}
+
+/// Print the known platforms, annotating the ones with pages in the cache
+fn print_platforms() {
+    // Without a cache, no platform has pages
+    let platform_dirs = Cache::list_platform_dirs().unwrap_or_default();
+    for os in &OsType::PLATFORMS {
+        if platform_dirs.iter().any(|dir| dir == os.name()) {
+            println!("{} (cached)", os.name());
+        } else {
+            println!("{}", os.name());
+        }
+    }
+}
+
 /// Show the config path (DEPRECATED)
-fn show_config_path() {
-    match get_config_path() {
+fn show_config_path(custom_config_path: Option<&Path>, custom_config_dir: Option<&Path>) {
+    match get_config_path(custom_config_path, custom_config_dir) {
         Ok((config_file_path, _)) => {
             println!("Config path is: {}", config_file_path.to_str().unwrap());
         }
         Err(ConfigError(msg)) => {
             eprintln!("Could not look up config_path: {}", msg);
-            process::exit(1);
+            process::exit(ExitCode::ConfigError as i32);
         }
         Err(_) => {
             eprintln!("Unknown error");
-            process::exit(1);
+            process::exit(ExitCode::ConfigError as i32);
         }
     }
 }
 
 /// Show file paths
-fn show_paths() {
-    let config_dir = get_config_dir().map_or_else(
+fn show_paths(custom_config_path: Option<&Path>, custom_config_dir: Option<&Path>) {
+    let config_dir = get_config_dir(custom_config_dir).map_or_else(
         |e| format!("[Error: {}]", e),
         |(mut path, source)| {
             path.push(""); // Trailing path separator
@@ -212,7 +1280,7 @@ fn show_paths() {
             }
         },
     );
-    let config_path = get_config_path().map_or_else(
+    let config_path = get_config_path(custom_config_path, custom_config_dir).map_or_else(
         |e| format!("[Error: {}]", e),
         |(path, _)| path.to_str().unwrap_or("[Invalid]").to_string(),
     );
@@ -221,7 +1289,12 @@ fn show_paths() {
         |(mut path, source)| {
             path.push(""); // Trailing path separator
             match path.to_str() {
-                Some(path) => format!("{} ({})", path, source),
+                Some(path) => format!(
+                    "{} ({}, format version {})",
+                    path,
+                    source,
+                    cache::FORMAT_VERSION
+                ),
                 None => "[Invalid]".to_string(),
             }
         },
@@ -243,66 +1316,75 @@ fn show_paths() {
 }
 
 /// Create seed config file and exit
-fn create_config_and_exit() {
-    match make_default_config() {
+fn create_config_and_exit(args: &Args) {
+    match make_default_config(
+        args.flag_config.as_deref(),
+        args.flag_config_dir.as_deref(),
+        args.flag_force,
+        args.flag_minimal,
+    ) {
         Ok(config_file_path) => {
             eprintln!(
                 "Successfully created seed config file here: {}",
                 config_file_path.to_str().unwrap()
             );
-            process::exit(0);
+            process::exit(ExitCode::Success as i32);
         }
         Err(ConfigError(msg)) => {
             eprintln!("Could not create seed config: {}", msg);
-            process::exit(1);
+            process::exit(ExitCode::ConfigError as i32);
         }
         Err(_) => {
             eprintln!("Unknown error");
-            process::exit(1);
+            process::exit(ExitCode::ConfigError as i32);
         }
     }
 }
 
-#[cfg(feature = "logging")]
-fn init_log() {
-    env_logger::init();
-}
-
-#[cfg(not(feature = "logging"))]
-fn init_log() {}
-
-#[cfg(target_os = "linux")]
-fn get_os() -> OsType {
-    OsType::Linux
+/// Initialize the logger.
+///
+/// Log output can be enabled with `--verbose` (repeat for more detail) or
+/// through the `RUST_LOG` env variable. Without either, nothing is logged.
+fn init_log(verbosity: usize) {
+    let mut builder = env_logger::Builder::new();
+    match env::var("RUST_LOG") {
+        Ok(filters) => {
+            builder.parse_filters(&filters);
+        }
+        // Without any filter, errors would still be logged
+        Err(_) if verbosity == 0 => {
+            builder.filter_level(LevelFilter::Off);
+        }
+        Err(_) => {}
+    }
+    let level = match verbosity {
+        0 => None,
+        1 => Some(LevelFilter::Info),
+        2 => Some(LevelFilter::Debug),
+        _ => Some(LevelFilter::Trace),
+    };
+    if let Some(level) = level {
+        builder.filter_module("tldr", level);
+    }
+    builder.init();
 }
 
-#[cfg(any(
-    target_os = "macos",
-    target_os = "freebsd",
-    target_os = "netbsd",
-    target_os = "openbsd",
-    target_os = "dragonfly"
-))]
-fn get_os() -> OsType {
-    OsType::OsX
+/// Map an OS name as returned by `std::env::consts::OS` to an `OsType`.
+fn get_os_from_name(os_name: &str) -> OsType {
+    match os_name {
+        "linux" => OsType::Linux,
+        "macos" | "dragonfly" => OsType::OsX,
+        "freebsd" => OsType::FreeBsd,
+        "netbsd" => OsType::NetBsd,
+        "openbsd" => OsType::OpenBsd,
+        "solaris" | "illumos" => OsType::SunOs,
+        "windows" => OsType::Windows,
+        _ => OsType::Other,
+    }
 }
 
-#[cfg(target_os = "windows")]
 fn get_os() -> OsType {
-    OsType::Windows
-}
-
-#[cfg(not(any(
-    target_os = "linux",
-    target_os = "macos",
-    target_os = "freebsd",
-    target_os = "netbsd",
-    target_os = "openbsd",
-    target_os = "dragonfly",
-    target_os = "windows"
-)))]
-fn get_os() -> OsType {
-    OsType::Other
+    get_os_from_name(env::consts::OS)
 }
 
 fn get_languages(env_lang: Option<&str>, env_language: Option<&str>) -> Vec<String> {
@@ -337,6 +1419,40 @@ fn get_languages(env_lang: Option<&str>, env_language: Option<&str>) -> Vec<Stri
     lang_list.into_iter().map(str::to_string).collect()
 }
 
+/// Parse the comma separated language list passed through `--language`.
+///
+/// The languages are tried in the given order, followed by English.
+fn get_languages_from_flag(flag_lang: &str) -> Result<Vec<String>, String> {
+    let mut lang_list = Vec::new();
+    for lang in flag_lang.split(',').map(str::trim) {
+        if !is_valid_language_code(lang) {
+            return Err(format!(
+                "Invalid language code \"{}\" (expected e.g. `de` or `pt_BR`)",
+                lang
+            ));
+        }
+        lang_list.push(lang);
+    }
+
+    lang_list.push("en");
+    lang_list.clear_duplicates();
+    Ok(lang_list.into_iter().map(str::to_string).collect())
+}
+
+/// Check whether a language code has the form `ll` or `ll_CC` (e.g. `de` or `pt_BR`).
+fn is_valid_language_code(code: &str) -> bool {
+    let bytes = code.as_bytes();
+    match bytes.len() {
+        2 => bytes.iter().all(u8::is_ascii_lowercase),
+        5 => {
+            bytes[..2].iter().all(u8::is_ascii_lowercase)
+                && bytes[2] == b'_'
+                && bytes[3..].iter().all(u8::is_ascii_uppercase)
+        }
+        _ => false,
+    }
+}
+
 fn get_languages_from_env() -> Vec<String> {
     get_languages(
         std::env::var("LANG").ok().as_deref(),
@@ -344,34 +1460,112 @@ fn get_languages_from_env() -> Vec<String> {
     )
 }
 
-fn main() {
-    // Initialize logger
-    init_log();
+/// Determine the languages from the `--language` flag or the environment.
+///
+/// Exits if the `--language` flag is invalid.
+fn get_languages_from_args(args: &Args) -> Vec<String> {
+    let languages = match args.flag_language {
+        Some(ref flag_lang) => get_languages_from_flag(flag_lang).unwrap_or_else(|msg| {
+            eprintln!("{}", msg);
+            process::exit(ExitCode::Failure as i32);
+        }),
+        None => get_languages_from_env(),
+    };
+    debug!("Using languages {:?}", languages);
+    languages
+}
+
+/// Return the cache and the languages to look up the page of `command` with.
+///
+/// The `[commands]` section of the config for `command` is applied, unless
+/// `--os` or `--language` are passed.
+fn command_lookup(
+    args: &Args,
+    cache: Cache,
+    config: &Config,
+    command: &str,
+) -> (Cache, Vec<String>) {
+    let command_config = config.commands.get(command);
+    let languages = match command_config.and_then(|c| c.language.as_ref()) {
+        Some(language) if args.flag_language.is_none() => {
+            let languages = get_languages_from_flag(language).unwrap_or_else(|msg| {
+                eprintln!(
+                    "Invalid language of [commands.{:?}] in the config: {}",
+                    command, msg
+                );
+                process::exit(ExitCode::ConfigError as i32);
+            });
+            debug!("Using languages {:?} of the config", languages);
+            languages
+        }
+        _ => get_languages_from_args(args),
+    };
+    let cache = match command_config.and_then(|c| c.platform) {
+        Some(os) if args.flag_os.is_none() => {
+            debug!("Using platform {:?} of the config", os);
+            cache.with_os(os)
+        }
+        _ => cache,
+    };
+    (cache, languages)
+}
 
+fn main() {
     // Parse arguments
-    let args: Args = Docopt::new(USAGE)
+    let mut args: Args = Docopt::new(USAGE)
         .and_then(|d| d.deserialize())
-        .unwrap_or_else(|e| e.exit());
+        .unwrap_or_else(|e| {
+            if e.fatal() {
+                eprintln!("{}", e);
+                process::exit(ExitCode::Failure as i32);
+            }
+            e.exit()
+        });
+
+    // Initialize logger
+    init_log(args.flag_verbose);
 
     // Show version and exit
     if args.flag_version {
         let os = get_os();
         println!("{} v{} ({})", NAME, VERSION, os);
-        process::exit(0);
+        process::exit(ExitCode::Success as i32);
+    }
+
+    // Show platforms and exit
+    if args.flag_platform_list {
+        print_platforms();
+        process::exit(ExitCode::Success as i32);
+    }
+
+    // Show the languages in the cache and exit
+    if args.flag_language_list {
+        let languages = Cache::count_pages_by_language().unwrap_or_else(|e| {
+            eprintln!("Could not list the languages: {}", e.message());
+            process::exit(e.exit_code() as i32);
+        });
+        for (language, count) in languages {
+            if args.flag_count {
+                println!("{}: {}", language, count);
+            } else {
+                println!("{}", language);
+            }
+        }
+        process::exit(ExitCode::Success as i32);
     }
 
     // Show config file and path, pass through
     if args.flag_config_path {
         eprintln!("Warning: The --config-path flag is deprecated, use --show-paths instead");
-        show_config_path();
+        show_config_path(args.flag_config.as_deref(), args.flag_config_dir.as_deref());
     }
     if args.flag_show_paths {
-        show_paths();
+        show_paths(args.flag_config.as_deref(), args.flag_config_dir.as_deref());
     }
 
     // Create a basic config and exit
     if args.flag_seed_config {
-        create_config_and_exit();
+        create_config_and_exit(&args);
     }
 
     // Determine the usage of styles
@@ -380,34 +1574,89 @@ fn main() {
     #[cfg(not(target_os = "windows"))]
     let ansi_support = true;
 
+    // With TEALDEER_FORCE_COLOR=1, always style the output using a fixed
+    // palette, so that the escape sequences don't depend on the environment
+    let force_color = env::var_os("TEALDEER_FORCE_COLOR").map_or(false, |value| value == "1");
+
     let enable_styles = match args.flag_color {
+        // Bypass styling entirely if instructed
+        _ if args.flag_no_styling => false,
+        // Disable styling
+        ColorOptions::Never => false,
+        // Force styling for reproducible output
+        _ if force_color => true,
         // Attempt to use styling if instructed
         ColorOptions::Always => true,
         // Enable styling if:
         // * There is `ansi_support`
         // * NO_COLOR env var isn't set: https://no-color.org/
-        // * The output stream is stdout (not being piped)
+        // * The output stream is stdout (not being piped or written to a file)
         ColorOptions::Auto => {
-            ansi_support && env::var_os("NO_COLOR").is_none() && atty::is(Stream::Stdout)
+            ansi_support
+                && env::var_os("NO_COLOR").is_none()
+                && args.flag_output.is_none()
+                && atty::is(Stream::Stdout)
         }
-        // Disable styling
-        ColorOptions::Never => false,
     };
 
     // Look up config file, if none is found fall back to default config.
-    let config = match Config::load(enable_styles) {
+    let mut config = match Config::load(
+        args.flag_config.as_deref(),
+        args.flag_config_dir.as_deref(),
+        args.flag_theme,
+        enable_styles,
+        args.flag_strict_config,
+    ) {
         Ok(config) => config,
         Err(ConfigError(msg)) => {
             eprintln!("Could not load config: {}", msg);
-            process::exit(1);
+            process::exit(ExitCode::ConfigError as i32);
         }
         Err(e) => {
             eprintln!("Could not load config: {}", e);
-            process::exit(1);
+            process::exit(ExitCode::ConfigError as i32);
         }
     };
+    if force_color && enable_styles {
+        config.style = StyleConfig::fixed_palette();
+    }
+    config.display.final_reset = enable_styles
+        && args.flag_color == ColorOptions::Always
+        && (args.flag_output.is_some() || !atty::is(Stream::Stdout));
+    config.display.examples_only = args.flag_examples_only;
+    config.display.line_numbers = args.flag_line_numbers;
+    config.display.example_filter = args.flag_grep.clone();
+    config.display.dedup_examples = args.flag_dedup;
+    for assignment in &args.flag_set {
+        match assignment.find('=') {
+            Some(pos) if pos > 0 => {
+                config.display.placeholder_values.insert(
+                    assignment[..pos].to_string(),
+                    assignment[pos + 1..].to_string(),
+                );
+            }
+            _ => {
+                eprintln!(
+                    "Invalid placeholder value: {} (expected KEY=VALUE)",
+                    assignment
+                );
+                process::exit(ExitCode::Failure as i32);
+            }
+        }
+    }
+    // The scripts of `--as-script` are shell scripts on every platform
+    if args.flag_placeholders == PlaceholderStyle::Positional {
+        config.display.positional_prefix = Some(if cfg!(windows) && !args.flag_as_script {
+            '%'
+        } else {
+            '$'
+        });
+    }
 
-    if args.flag_pager || config.display.use_pager {
+    if (args.flag_pager || config.display.use_pager)
+        && args.flag_output.is_none()
+        && !args.flag_oneline
+    {
         configure_pager();
     }
 
@@ -416,13 +1665,25 @@ fn main() {
         Some(os) => os,
         None => get_os(),
     };
+    debug!("Using platform {:?}", os);
 
     // Initialize cache
-    let cache = Cache::new(ARCHIVE_URL, os);
+    let cache = Cache::new(config.updates.archive_url.as_str(), os)
+        .with_common(!args.flag_no_common)
+        .with_platform_order(if args.flag_os.is_none() {
+            config.directories.platform_order.clone()
+        } else {
+            Vec::new()
+        })
+        .with_user_agent(config.updates.user_agent.as_str())
+        .with_headers(config.updates.headers.clone())
+        .with_delta_url(config.updates.delta_url.clone())
+        .with_networking(config.updates.networking)
+        .with_readonly(config.directories.cache_readonly);
 
     // Clear cache, pass through
     if args.flag_clear_cache {
-        clear_cache(args.flag_quiet);
+        clear_cache(&cache, args.flag_quiet);
     }
 
     // Update cache, pass through
@@ -433,66 +1694,467 @@ fn main() {
         false
     };
 
+    // Report whether a newer release is available and exit
+    if args.flag_check_update {
+        check_for_release(&cache, args.flag_quiet);
+    }
+
+    // Run the rendering benchmark and exit
+    if let Some(runs) = args.flag_benchmark {
+        if let Err(msg) = run_benchmark(runs, &config) {
+            eprintln!("{}", msg);
+            process::exit(ExitCode::Failure as i32);
+        }
+        process::exit(ExitCode::Success as i32);
+    }
+
+    // Render the style preview and exit
+    if args.flag_theme_preview {
+        if let Err(msg) = open_output(args.flag_output.as_deref()).and_then(|mut output| {
+            print_theme_preview(
+                &mut output,
+                !args.flag_no_styling,
+                get_output_width(&args, &config),
+                &config,
+            )
+        }) {
+            eprintln!("{}", msg);
+            process::exit(ExitCode::Failure as i32);
+        }
+        process::exit(ExitCode::Success as i32);
+    }
+
     // Render local file and exit
     if let Some(ref file) = args.flag_render {
         let path = PageLookupResult::with_page(PathBuf::from(file));
-        if let Err(msg) = print_page(&path, args.flag_markdown, &config) {
+        let render = || {
+            open_output(args.flag_output.as_deref()).and_then(|output| {
+                let mut output = LineLimit::new(output, get_max_lines(&args));
+                if args.flag_hexdump {
+                    print_hexdump(&path, &mut output)?;
+                } else if args.flag_as_script {
+                    print_page_script(&path, &mut output, &config)?;
+                } else {
+                    print_page(
+                        &path,
+                        &mut output,
+                        args.flag_format,
+                        args.flag_markdown,
+                        !args.flag_no_styling,
+                        get_output_width(&args, &config),
+                        &config,
+                    )?;
+                }
+                output
+                    .finish(args.flag_quiet)
+                    .map_err(|e| format!("Could not write output: {}", e))
+            })
+        };
+        let result = if args.flag_watch {
+            watch_page(Path::new(file), render)
+        } else {
+            render()
+        };
+        if let Err(msg) = result {
             eprintln!("{}", msg);
-            process::exit(1);
+            process::exit(ExitCode::Failure as i32);
         } else {
-            process::exit(0);
+            process::exit(ExitCode::Success as i32);
         };
     }
+    if args.flag_watch {
+        eprintln!("--watch can only be used with --render <file>");
+        process::exit(ExitCode::Failure as i32);
+    }
+
+    // Compare custom page with upstream page and exit
+    if let Some(ref command) = args.flag_diff {
+        let languages = get_languages_from_args(&args);
 
-    // List cached commands and exit
-    if args.flag_list {
         if !cache_updated {
             // Check cache for freshness
-            check_cache(&args, enable_styles);
+            check_cache(&args, &cache, &config, enable_styles);
         }
 
-        // Get list of pages
-        let pages = cache.list_pages().unwrap_or_else(|e| {
-            eprintln!("Could not get list of pages: {}", e.message());
-            process::exit(1);
+        match print_custom_page_diff(&cache, command, &languages, &config) {
+            Ok(true) => process::exit(ExitCode::Success as i32),
+            Ok(false) => process::exit(ExitCode::PageNotFound as i32),
+            Err(msg) => {
+                eprintln!("{}", msg);
+                process::exit(ExitCode::Failure as i32);
+            }
+        }
+    }
+
+    // Print the upstream URL of a page and exit
+    if let Some(ref command) = args.flag_page_url {
+        let languages = get_languages_from_args(&args);
+
+        if !cache_updated {
+            // Check cache for freshness
+            check_cache(&args, &cache, &config, enable_styles);
+        }
+
+        // Commands with spaces are looked up like multiple arguments
+        let command = command.split_whitespace().collect::<Vec<_>>().join("-");
+        match get_page_url(&cache, &command, &languages, &config) {
+            Ok(url) => println!("{}", url),
+            Err(msg) => {
+                eprintln!("{}", msg);
+                process::exit(ExitCode::PageNotFound as i32);
+            }
+        }
+        process::exit(ExitCode::Success as i32);
+    }
+
+    // Print the path of the page that would be rendered and exit
+    if let Some(ref command) = args.flag_which {
+        if !cache_updated {
+            // Check cache for freshness
+            check_cache(&args, &cache, &config, enable_styles);
+        }
+
+        // Commands with spaces are looked up like multiple arguments
+        let words: Vec<String> = command.split_whitespace().map(String::from).collect();
+        let (cache, languages) = command_lookup(&args, cache, &config, &words.join("-"));
+        let page = find_command_page(&cache, &words, &languages, &config).unwrap_or_else(|| {
+            eprintln!("Page {} not found", words.join("-"));
+            process::exit(ExitCode::PageNotFound as i32);
         });
+        for path in page.paths() {
+            let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+            println!("{}", path.display());
+        }
+        process::exit(ExitCode::Success as i32);
+    }
+
+    // Check that the listed pages are available and exit
+    if let Some(ref list_path) = args.flag_prefetch {
+        let languages = get_languages_from_args(&args);
 
-        // Print pages
-        println!("{}", pages.join("\n"));
-        process::exit(0);
+        if !cache_updated {
+            // Check cache for freshness
+            check_cache(&args, &cache, &config, enable_styles);
+        }
+
+        match check_prefetch_list(&cache, list_path, &languages, args.flag_quiet, &config) {
+            Ok(0) => process::exit(ExitCode::Success as i32),
+            Ok(_) => process::exit(ExitCode::PageNotFound as i32),
+            Err(msg) => {
+                eprintln!("{}", msg);
+                process::exit(ExitCode::Failure as i32);
+            }
+        }
     }
 
-    // Show command from cache
-    if let Some(ref command) = args.arg_command {
-        let command = command.join("-");
+    // Write the index of all pages and exit
+    if args.flag_dump_index {
+        if !cache_updated {
+            // Check cache for freshness
+            check_cache(&args, &cache, &config, enable_styles);
+        }
+
+        let index = Cache::page_index(config.directories.custom_pages_dir.as_deref())
+            .unwrap_or_else(|e| {
+                eprintln!("Could not get the index of the pages: {}", e.message());
+                process::exit(e.exit_code() as i32);
+            });
+        if let Err(msg) = open_output(args.flag_output.as_deref())
+            .and_then(|mut output| print_index(&index, args.flag_format, &mut output))
+        {
+            eprintln!("{}", msg);
+            process::exit(ExitCode::Failure as i32);
+        }
+        process::exit(ExitCode::Success as i32);
+    }
+
+    // Render all pages listed in a manifest into one document
+    if let Some(ref manifest_path) = args.flag_manifest {
+        if args.flag_format == OutputFormat::Json {
+            eprintln!("The --manifest flag does not support --format json.");
+            process::exit(ExitCode::Failure as i32);
+        }
+        let languages = get_languages_from_args(&args);
+
+        if !cache_updated {
+            // Check cache for freshness
+            check_cache(&args, &cache, &config, enable_styles);
+        }
+
+        match read_manifest(manifest_path).and_then(|commands| {
+            let mut output = open_output(args.flag_output.as_deref())?;
+            print_manifest(
+                &cache,
+                &commands,
+                &languages,
+                &args,
+                &mut output,
+                enable_styles,
+                &config,
+            )
+        }) {
+            Ok(0) => process::exit(ExitCode::Success as i32),
+            Ok(_) => process::exit(ExitCode::PageNotFound as i32),
+            Err(msg) => {
+                eprintln!("{}", msg);
+                process::exit(ExitCode::Failure as i32);
+            }
+        }
+    }
 
+    // Export the cache and exit
+    if let Some(ref export_path) = args.flag_export {
         if !cache_updated {
             // Check cache for freshness
-            check_cache(&args, enable_styles);
+            check_cache(&args, &cache, &config, enable_styles);
+        }
+
+        let custom_pages_dir = if args.flag_with_custom_pages {
+            config.directories.custom_pages_dir.as_deref()
+        } else {
+            None
+        };
+        if let Err(e) = Cache::export(export_path, custom_pages_dir) {
+            eprintln!("{}", e.message());
+            process::exit(e.exit_code() as i32);
+        }
+        if !args.flag_quiet {
+            eprintln!("Exported cache to {}", export_path.display());
+        }
+        process::exit(ExitCode::Success as i32);
+    }
+
+    // List cached commands and exit
+    if args.flag_list {
+        if !cache_updated {
+            // Check cache for freshness
+            check_cache(&args, &cache, &config, enable_styles);
+        }
+
+        // Print the number of pages of each platform
+        if args.flag_count && args.flag_all_platforms {
+            let (counts, total) =
+                Cache::count_pages_by_platform(config.directories.custom_pages_dir.as_deref())
+                    .unwrap_or_else(|e| {
+                        eprintln!("Could not count the pages: {}", e.message());
+                        process::exit(e.exit_code() as i32);
+                    });
+            for (platform, count) in counts {
+                println!("{}: {}", platform, count);
+            }
+            println!("total: {}", total);
+            process::exit(ExitCode::Success as i32);
+        }
+
+        // Print the pages under a header for each platform
+        if args.flag_group_by_platform {
+            let groups = cache
+                .list_pages_by_platform(
+                    config.directories.custom_pages_dir.as_deref(),
+                    args.flag_custom_only,
+                )
+                .unwrap_or_else(|e| {
+                    eprintln!("Could not get list of pages: {}", e.message());
+                    process::exit(e.exit_code() as i32);
+                });
+            if let Err(msg) = print_grouped_list(&groups, args.flag_format, &mut io::stdout()) {
+                eprintln!("{}", msg);
+                process::exit(ExitCode::Failure as i32);
+            }
+            process::exit(ExitCode::Success as i32);
+        }
+
+        // Get list of pages
+        let pages = cache
+            .list_pages(
+                config.directories.custom_pages_dir.as_deref(),
+                args.flag_custom_only,
+                args.flag_sort,
+                args.flag_offset,
+                args.flag_limit,
+            )
+            .unwrap_or_else(|e| {
+                eprintln!("Could not get list of pages: {}", e.message());
+                process::exit(e.exit_code() as i32);
+            });
+
+        // Print pages, custom pages are only marked on a terminal (with
+        // styling), so that the list can be processed by scripts
+        if args.flag_count {
+            println!("{}", pages.len());
+        } else if args.flag_null {
+            // For `xargs -0`, custom pages are never marked
+            for page in &pages {
+                print!("{}\0", page.name);
+            }
+        } else if args.flag_long {
+            let languages = get_languages_from_args(&args);
+            print_long_list(
+                &cache,
+                &pages,
+                enable_styles,
+                &languages,
+                get_output_width(&args, &config),
+                &config,
+            );
+        } else {
+            let labels: Vec<Cow<str>> = pages
+                .iter()
+                .map(|page| list_entry_label(page, enable_styles))
+                .collect();
+            println!("{}", labels.join("\n"));
         }
+        process::exit(ExitCode::Success as i32);
+    }
 
-        let languages = args
-            .flag_language
-            .map_or_else(get_languages_from_env, |flag_lang| vec![flag_lang]);
+    // Pick the command to show interactively
+    if args.flag_interactive {
+        match pick_page(&cache, &config) {
+            Ok(Some(name)) => args.arg_command = Some(vec![name]),
+            Ok(None) => process::exit(ExitCode::Success as i32),
+            Err(msg) => {
+                eprintln!("{}", msg);
+                process::exit(ExitCode::Failure as i32);
+            }
+        }
+    }
+
+    // Explain a command: its page is shown below the summary of its man page
+    if let Some(ref command) = args.flag_explain {
+        args.arg_command = Some(command.split_whitespace().map(String::from).collect());
+    }
+
+    // Show command from cache
+    if let Some(ref words) = args.arg_command {
+        let command = words.join("-");
+        config.display.requested_command = Some(words.join(" "));
+
+        // Check cache for freshness
+        let cache_stale = !cache_updated && check_cache(&args, &cache, &config, enable_styles);
+
+        let (cache, languages) = command_lookup(&args, cache, &config, &command);
 
         // Search for command in cache
-        if let Some(page) = cache.find_page(
-            &command,
-            &languages,
-            config.directories.custom_pages_dir.as_deref(),
-        ) {
-            if let Err(msg) = print_page(&page, args.flag_markdown, &config) {
+        let page = find_command_page(&cache, words, &languages, &config);
+
+        // Dump the bytes of the page files
+        if args.flag_hexdump {
+            let page = page.unwrap_or_else(|| {
+                eprintln!("Page {} not found in cache", &command);
+                process::exit(ExitCode::PageNotFound as i32);
+            });
+            if let Err(msg) = open_output(args.flag_output.as_deref())
+                .and_then(|mut output| print_hexdump(&page, &mut output))
+            {
+                eprintln!("{}", msg);
+                process::exit(ExitCode::Failure as i32);
+            }
+            process::exit(ExitCode::Success as i32);
+        }
+
+        // Only print the first example, missing pages produce no output
+        if args.flag_oneline {
+            match page.map(|page| {
+                open_output(args.flag_output.as_deref())
+                    .and_then(|mut output| print_first_example(&page, &mut output, &config))
+            }) {
+                Some(Ok(true)) => process::exit(ExitCode::Success as i32),
+                Some(Err(msg)) => {
+                    eprintln!("{}", msg);
+                    process::exit(ExitCode::Failure as i32);
+                }
+                Some(Ok(false)) | None => {}
+            }
+            process::exit(ExitCode::PageNotFound as i32);
+        }
+
+        if let Some(page) = page {
+            if config.display.hyperlink_see_also {
+                config.display.see_also_urls = see_also_urls(&cache, &page, &languages, &config);
+            }
+
+            // Fail instead of rendering a page without examples, so that
+            // scripts can detect it (the other outputs are not filtered)
+            let rendered = !args.flag_markdown
+                && !args.flag_as_script
+                && args.flag_format == OutputFormat::Text;
+            if let (Some(pattern), true) = (&config.display.example_filter, rendered) {
+                match count_rendered_examples(&page, &config) {
+                    Ok(0) => {
+                        if !args.flag_quiet {
+                            eprintln!(
+                                "{}",
+                                config
+                                    .display
+                                    .no_match_message
+                                    .replace("{pattern}", pattern)
+                            );
+                        }
+                        process::exit(ExitCode::PageNotFound as i32);
+                    }
+                    Ok(_) => {}
+                    Err(msg) => {
+                        eprintln!("{}", msg);
+                        process::exit(ExitCode::Failure as i32);
+                    }
+                }
+            }
+            let man_summary = if rendered && args.flag_explain.is_some() {
+                man::summary(&command)
+            } else {
+                None
+            };
+            if let Err(msg) = open_output(args.flag_output.as_deref()).and_then(|output| {
+                let mut output = LineLimit::new(output, get_max_lines(&args));
+                if args.flag_as_script {
+                    print_page_script(&page, &mut output, &config)?;
+                    return output
+                        .finish(args.flag_quiet)
+                        .map_err(|e| format!("Could not write output: {}", e));
+                }
+                if let Some(ref summary) = man_summary {
+                    print_man_summary(summary, &mut output, !args.flag_no_styling, &config)?;
+                }
+                print_page(
+                    &page,
+                    &mut output,
+                    args.flag_format,
+                    args.flag_markdown,
+                    !args.flag_no_styling,
+                    get_output_width(&args, &config),
+                    &config,
+                )?;
+                if config.display.show_resolution_caption
+                    && enable_styles
+                    && !args.flag_quiet
+                    && !args.flag_markdown
+                    && args.flag_format == OutputFormat::Text
+                {
+                    print_resolution_caption(&page, &mut output, &config)?;
+                }
+                output
+                    .finish(args.flag_quiet)
+                    .map_err(|e| format!("Could not write output: {}", e))
+            }) {
                 eprintln!("{}", msg);
-                process::exit(1);
+                process::exit(ExitCode::Failure as i32);
+            }
+            // Let wrappers detect a stale cache through the exit status
+            if cache_stale {
+                process::exit(i32::from(config.updates.stale_exit_code));
             }
-            process::exit(0);
+            process::exit(ExitCode::Success as i32);
         } else {
             if !args.flag_quiet {
                 eprintln!("Page {} not found in cache", &command);
                 eprintln!("Try updating with `tldr --update`, or submit a pull request to:");
                 eprintln!("https://github.com/tldr-pages/tldr");
             }
-            process::exit(1);
+            if let Some(ref command_line) = config.display.on_not_found_command {
+                run_not_found_command(command_line, &command, args.flag_quiet);
+            }
+            print_json_error(&args, "not_found", Some(&words.join(" ")));
+            process::exit(ExitCode::PageNotFound as i32);
         }
     }
 
@@ -500,19 +2162,48 @@ fn main() {
     if !(args.flag_update || args.flag_clear_cache || args.flag_config_path || args.flag_show_paths)
     {
         eprintln!("{}", USAGE);
-        process::exit(1);
+        process::exit(ExitCode::Failure as i32);
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::{get_languages, Args, OsType, USAGE};
+    use crate::{
+        get_languages, get_languages_from_flag, get_os_from_name, hexdump_lines, is_newer_version,
+        Args, LineLimit, OsType, USAGE,
+    };
     use docopt::{Docopt, Error};
+    use std::io::Write;
 
     fn test_helper(argv: &[&str]) -> Result<Args, Error> {
         Docopt::new(USAGE).and_then(|d| d.argv(argv.iter()).deserialize())
     }
 
+    #[test]
+    fn test_line_limit() {
+        let mut output = Vec::new();
+        let mut limit = LineLimit::new(&mut output, 2);
+        write!(limit, "one\ntw").unwrap();
+        write!(limit, "o\nthree\nfour\n").unwrap();
+        limit.finish(false).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "one\ntwo\n\u{2026} (truncated, 2 more lines)\n"
+        );
+
+        let mut output = Vec::new();
+        let mut limit = LineLimit::new(&mut output, 2);
+        write!(limit, "one\ntwo\nthree\n").unwrap();
+        limit.finish(true).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "one\ntwo\n");
+
+        let mut output = Vec::new();
+        let mut limit = LineLimit::new(&mut output, usize::MAX);
+        write!(limit, "one\ntwo\n").unwrap();
+        limit.finish(false).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "one\ntwo\n");
+    }
+
     #[test]
     fn test_docopt_os_case_insensitive() {
         let argv = vec!["cp", "--os", "LiNuX"];
@@ -520,6 +2211,56 @@ mod test {
         assert_eq!(OsType::Linux, os);
     }
 
+    #[test]
+    fn test_docopt_os_bsd() {
+        let argv = vec!["cp", "--os", "netbsd"];
+        let os = test_helper(&argv).unwrap().flag_os.unwrap();
+        assert_eq!(OsType::NetBsd, os);
+    }
+
+    #[test]
+    fn test_docopt_set_repeated() {
+        let argv = vec!["tldr", "--set", "file=a.tar", "--set", "dir=b", "tar"];
+        let args = test_helper(&argv).unwrap();
+        assert_eq!(args.flag_set, vec!["file=a.tar", "dir=b"]);
+        assert_eq!(args.arg_command, Some(vec!["tar".to_string()]));
+    }
+
+    #[test]
+    fn test_os_from_name() {
+        assert_eq!(get_os_from_name("linux"), OsType::Linux);
+        assert_eq!(get_os_from_name("macos"), OsType::OsX);
+        assert_eq!(get_os_from_name("freebsd"), OsType::FreeBsd);
+        assert_eq!(get_os_from_name("netbsd"), OsType::NetBsd);
+        assert_eq!(get_os_from_name("openbsd"), OsType::OpenBsd);
+        assert_eq!(get_os_from_name("windows"), OsType::Windows);
+        assert_eq!(get_os_from_name("haiku"), OsType::Other);
+    }
+
+    #[test]
+    fn test_is_newer_version() {
+        assert!(is_newer_version("1.5.0", "1.4.1"));
+        assert!(is_newer_version("1.10.0", "1.9.0"));
+        assert!(is_newer_version("2.0.0", "1.4.1"));
+        assert!(!is_newer_version("1.4.1", "1.4.1"));
+        assert!(!is_newer_version("1.4.0", "1.4.1"));
+        assert!(!is_newer_version("1.4.1-rc1", "1.4.1"));
+    }
+
+    #[test]
+    fn test_hexdump_lines() {
+        assert_eq!(
+            hexdump_lines(b"\xef\xbb\xbf# tar\r\n\n> Archiving utility.\r\n"),
+            vec![
+                "00000000  ef bb bf 23 20 74 61 72  0d 0a 0a 3e 20 41 72 63  |...# tar...> Arc|",
+                "00000010  68 69 76 69 6e 67 20 75  74 69 6c 69 74 79 2e 0d  |hiving utility..|",
+                "00000020  0a                                                |.|",
+                "00000021",
+            ]
+        );
+        assert_eq!(hexdump_lines(b""), vec!["00000000"]);
+    }
+
     #[test]
     fn test_docopt_expect_error() {
         let argv = vec!["cp", "--os", "lindows"];
@@ -568,5 +2309,23 @@ mod test {
             let lang_list = get_languages(Some("de"), Some("fr:de:cn:de"));
             assert_eq!(lang_list, vec!["fr", "de", "cn", "en"]);
         }
+
+        #[test]
+        fn flag_priority_list() {
+            let lang_list = get_languages_from_flag("de,fr").unwrap();
+            assert_eq!(lang_list, vec!["de", "fr", "en"]);
+            let lang_list = get_languages_from_flag("pt_BR, en, de").unwrap();
+            assert_eq!(lang_list, vec!["pt_BR", "en", "de"]);
+            let lang_list = get_languages_from_flag("it,it").unwrap();
+            assert_eq!(lang_list, vec!["it", "en"]);
+        }
+
+        #[test]
+        fn flag_invalid_language_code() {
+            assert!(get_languages_from_flag("german").is_err());
+            assert!(get_languages_from_flag("de,").is_err());
+            assert!(get_languages_from_flag("DE").is_err());
+            assert!(get_languages_from_flag("pt-BR").is_err());
+        }
     }
 }
@@ -0,0 +1,134 @@
+//! The summary of the local man page of a command, used with `--explain`.
+//!
+//! The page is formatted by `man` itself (with `cat` as the pager), and the
+//! NAME and SYNOPSIS sections are taken from its plain output. Without `man`
+//! or without a page for the command, there is no summary.
+
+use std::process::{Command, Stdio};
+
+use log::debug;
+
+/// The NAME and SYNOPSIS sections of a man page.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ManSummary {
+    /// The one-line description (e.g. `ls - list directory contents`)
+    pub name: Option<String>,
+    /// The lines of the synopsis, one or more per form of the command
+    pub synopsis: Vec<String>,
+}
+
+/// Return the summary of the man page of `command`, or `None` if `man` isn't
+/// available or has no page for it.
+pub fn summary(command: &str) -> Option<ManSummary> {
+    let output = Command::new("man")
+        .arg(command)
+        .env("MANPAGER", "cat")
+        .env("PAGER", "cat")
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .map_err(|e| debug!("Could not run man: {}", e))
+        .ok()?;
+    if !output.status.success() {
+        debug!("No man page for {} ({})", command, output.status);
+        return None;
+    }
+    parse(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Remove the formatting of man output: overstrikes (`X\x08X` for bold,
+/// `_\x08X` for underline) and SGR escape sequences.
+fn plain_text(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\x08' => {
+                plain.pop();
+            }
+            '\x1b' => {
+                if chars.peek() == Some(&'[') {
+                    // Skip the parameters up to the final byte
+                    chars.next();
+                    chars.by_ref().find(|c| ('@'..='~').contains(c));
+                }
+            }
+            c => plain.push(c),
+        }
+    }
+    plain
+}
+
+/// Parse the NAME and SYNOPSIS sections of man output, `None` if it has none.
+fn parse(text: &str) -> Option<ManSummary> {
+    let mut summary = ManSummary::default();
+    let mut section = String::new();
+    for line in plain_text(text).lines() {
+        // Section headers (and the page header and footer) are not indented
+        if !line.starts_with(char::is_whitespace) {
+            if !line.is_empty() {
+                section = line.trim().to_string();
+            }
+            continue;
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        match section.as_str() {
+            // A long description is wrapped over several lines
+            "NAME" => match summary.name {
+                Some(ref mut name) => {
+                    name.push(' ');
+                    name.push_str(line);
+                }
+                None => summary.name = Some(line.to_string()),
+            },
+            "SYNOPSIS" => summary.synopsis.push(line.to_string()),
+            _ => {}
+        }
+    }
+    if summary.name.is_none() && summary.synopsis.is_empty() {
+        None
+    } else {
+        Some(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, plain_text, ManSummary};
+
+    #[test]
+    fn test_plain_text() {
+        assert_eq!(plain_text("N\x08NA\x08AM\x08ME\x08E"), "NAME");
+        assert_eq!(plain_text("_\x08f_\x08i_\x08l_\x08e"), "file");
+        assert_eq!(
+            plain_text("\x1b[1mNAME\x1b[0m \x1b[4mfile\x1b[24m"),
+            "NAME file"
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        let text = "LS(1)                     User Commands                    LS(1)\n\
+                    \n\
+                    N\x08NA\x08AM\x08ME\x08E\n       \
+                           ls - list directory\n       \
+                           contents\n\
+                    \n\
+                    SYNOPSIS\n       \
+                           ls [OPTION]... [FILE]...\n\
+                    \n\
+                    DESCRIPTION\n       \
+                           List information about the FILEs.\n";
+        assert_eq!(
+            parse(text),
+            Some(ManSummary {
+                name: Some("ls - list directory contents".to_string()),
+                synopsis: vec!["ls [OPTION]... [FILE]...".to_string()],
+            })
+        );
+        assert_eq!(parse("DESCRIPTION\n       Nothing.\n"), None);
+    }
+}
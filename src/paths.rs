@@ -0,0 +1,157 @@
+//! The default locations of the config, cache and data directories.
+//!
+//! On every Unix target, the XDG base directory variables (`XDG_CONFIG_HOME`,
+//! `XDG_CACHE_HOME` and `XDG_DATA_HOME`) take precedence if they are set to an
+//! absolute path. Otherwise, the OS conventions are followed (e.g.
+//! `~/.cache` on Linux, `~/Library/Caches` on macOS), with `$HOME/.config`,
+//! `$HOME/.cache` and `$HOME/.local/share` as a last resort for targets that
+//! have no known convention. On Windows, the known folders are used (the
+//! roaming `%APPDATA%` for the config, the local `%LOCALAPPDATA%` for the
+//! cache and the data).
+
+use std::env;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+use app_dirs::{get_app_root, AppDataType};
+
+/// A kind of directory of tealdeer.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DirKind {
+    Config,
+    Cache,
+    /// The user data (the default custom pages directory is in it)
+    Data,
+}
+
+impl DirKind {
+    /// The XDG base directory variable of this kind.
+    #[cfg(unix)]
+    fn xdg_var(self) -> &'static str {
+        match self {
+            Self::Config => "XDG_CONFIG_HOME",
+            Self::Cache => "XDG_CACHE_HOME",
+            Self::Data => "XDG_DATA_HOME",
+        }
+    }
+
+    /// The default of the XDG base directory, relative to the home directory.
+    #[cfg(unix)]
+    fn xdg_default(self) -> &'static str {
+        match self {
+            Self::Config => ".config",
+            Self::Cache => ".cache",
+            Self::Data => ".local/share",
+        }
+    }
+
+    fn app_data_type(self) -> AppDataType {
+        match self {
+            Self::Config => AppDataType::UserConfig,
+            Self::Cache => AppDataType::UserCache,
+            Self::Data => AppDataType::UserData,
+        }
+    }
+}
+
+/// Return the default directory of `kind` for tealdeer, or `None` if it can't
+/// be determined. The directory is not created.
+pub fn default_dir(kind: DirKind) -> Option<PathBuf> {
+    resolve(kind, env::var_os, || {
+        get_app_root(kind.app_data_type(), &crate::APP_INFO).ok()
+    })
+}
+
+/// Return the directory of `kind`, with the environment variables of `var`
+/// and the directory of the OS convention of `os_convention`.
+#[cfg(unix)]
+fn resolve<V, O>(kind: DirKind, var: V, os_convention: O) -> Option<PathBuf>
+where
+    V: Fn(&'static str) -> Option<OsString>,
+    O: FnOnce() -> Option<PathBuf>,
+{
+    // Relative paths are invalid according to the XDG spec, and ignored
+    let absolute = |name: &'static str| {
+        var(name)
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+    };
+    absolute(kind.xdg_var())
+        .map(|base| base.join(crate::NAME))
+        .or_else(os_convention)
+        .or_else(|| absolute("HOME").map(|home| home.join(kind.xdg_default()).join(crate::NAME)))
+}
+
+/// Return the directory of `kind` according to the OS convention of
+/// `os_convention`, see the module documentation.
+#[cfg(not(unix))]
+fn resolve<V, O>(_kind: DirKind, _var: V, os_convention: O) -> Option<PathBuf>
+where
+    V: Fn(&'static str) -> Option<OsString>,
+    O: FnOnce() -> Option<PathBuf>,
+{
+    os_convention()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::{resolve, DirKind};
+    use std::ffi::OsString;
+    use std::path::PathBuf;
+
+    /// Return a lookup of the environment variables `vars`.
+    fn env<'a>(vars: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<OsString> + 'a {
+        move |name| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| OsString::from(value))
+        }
+    }
+
+    #[test]
+    fn test_xdg_precedence() {
+        let vars = [
+            ("XDG_CACHE_HOME", "/xdg/cache"),
+            ("XDG_CONFIG_HOME", "/xdg/config"),
+            ("HOME", "/home/user"),
+        ];
+        let os_convention = || Some(PathBuf::from("/Library/Caches/tealdeer"));
+        assert_eq!(
+            resolve(DirKind::Cache, env(&vars), os_convention),
+            Some(PathBuf::from("/xdg/cache/tealdeer"))
+        );
+        assert_eq!(
+            resolve(DirKind::Config, env(&vars), os_convention),
+            Some(PathBuf::from("/xdg/config/tealdeer"))
+        );
+
+        // Unset or relative, the OS convention is used
+        assert_eq!(
+            resolve(DirKind::Data, env(&vars), os_convention),
+            Some(PathBuf::from("/Library/Caches/tealdeer"))
+        );
+        assert_eq!(
+            resolve(
+                DirKind::Cache,
+                env(&[("XDG_CACHE_HOME", "relative/cache")]),
+                os_convention
+            ),
+            Some(PathBuf::from("/Library/Caches/tealdeer"))
+        );
+    }
+
+    #[test]
+    fn test_home_fallback() {
+        // Targets without a known convention
+        let vars = [("HOME", "/home/user")];
+        assert_eq!(
+            resolve(DirKind::Cache, env(&vars), || None),
+            Some(PathBuf::from("/home/user/.cache/tealdeer"))
+        );
+        assert_eq!(
+            resolve(DirKind::Data, env(&vars), || None),
+            Some(PathBuf::from("/home/user/.local/share/tealdeer"))
+        );
+        assert_eq!(resolve(DirKind::Config, env(&[]), || None), None);
+    }
+}
@@ -0,0 +1,131 @@
+//! The shell script representation of pages, used with `--as-script`.
+
+use std::io::Write;
+
+use crate::error::TealdeerError::{self, WriteError};
+use crate::types::LineType;
+
+/// The first line of the generated scripts.
+const SHEBANG: &str = "#!/bin/sh";
+
+/// Return the placeholders in {{ curly braces }} of example code.
+fn placeholders(code: &str) -> Vec<&str> {
+    let mut placeholders = Vec::new();
+    let mut rest = code;
+    while let Some(start) = rest.find("{{") {
+        match rest[start + 2..].find("}}") {
+            Some(len) => {
+                placeholders.push(&rest[start + 2..start + 2 + len]);
+                rest = &rest[start + 4 + len..];
+            }
+            None => break,
+        }
+    }
+    placeholders
+}
+
+/// Write the examples of a page (and its patch) as a shell script.
+///
+/// The page description and the example descriptions become comments. Example
+/// code with placeholders that have not been substituted (with `--set`) is
+/// commented out below a `# TODO` marker, so that the script only runs
+/// complete commands.
+pub fn write_script<I, W>(tokens: I, writer: &mut W) -> Result<(), TealdeerError>
+where
+    I: IntoIterator<Item = LineType>,
+    W: Write,
+{
+    let mut script = format!("{}\n", SHEBANG);
+    let mut has_title = false;
+    for token in tokens {
+        match token {
+            // The patch of a page has a title as well
+            LineType::Title(title) if !has_title => {
+                script.push_str(&format!("\n# {}\n", title));
+                has_title = true;
+            }
+            LineType::Description(text) => script.push_str(&format!("# {}\n", text)),
+            LineType::MoreInfo(url) => script.push_str(&format!("# More information: {}\n", url)),
+            LineType::SeeAlso(commands) => {
+                script.push_str(&format!("# See also: {}\n", commands.join(", ")));
+            }
+            LineType::Synopsis(synopsis) => script.push_str(&format!("# Usage: {}\n", synopsis)),
+            LineType::ExampleText(text) => script.push_str(&format!("\n# {}\n", text)),
+            LineType::ExampleCode(code) => {
+                let placeholders = placeholders(&code);
+                if placeholders.is_empty() {
+                    script.push_str(&format!("{}\n", code));
+                } else {
+                    script.push_str(&format!(
+                        "# TODO: replace {}\n# {}\n",
+                        placeholders
+                            .iter()
+                            .map(|placeholder| format!("{{{{{}}}}}", placeholder))
+                            .collect::<Vec<_>>()
+                            .join(" "),
+                        code
+                    ));
+                }
+            }
+            LineType::Title(_)
+            | LineType::Empty
+            | LineType::CodeLanguage(_)
+            | LineType::Other(_) => {}
+        }
+    }
+
+    writer
+        .write_all(script.as_bytes())
+        .and_then(|()| writer.flush())
+        .map_err(|e| WriteError(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{placeholders, write_script};
+    use crate::types::LineType;
+
+    #[test]
+    fn test_placeholders() {
+        assert_eq!(
+            placeholders("tar cf {{target.tar}} {{file1 file2}}"),
+            vec!["target.tar", "file1 file2"]
+        );
+        assert!(placeholders("tar cf backup.tar {{unterminated").is_empty());
+    }
+
+    #[test]
+    fn test_write_script() {
+        let tokens = vec![
+            LineType::Title("tar".into()),
+            LineType::Empty,
+            LineType::Description("Archiving utility.".into()),
+            LineType::MoreInfo("https://example.com/tar".into()),
+            LineType::Empty,
+            LineType::ExampleText("List the contents:".into()),
+            LineType::Empty,
+            LineType::ExampleCode("tar tf backup.tar".into()),
+            LineType::Empty,
+            LineType::ExampleText("Create an archive:".into()),
+            LineType::Empty,
+            LineType::ExampleCode("tar cf {{target.tar}} {{file}}".into()),
+        ];
+        let mut output = Vec::new();
+        write_script(tokens, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "#!/bin/sh\n\
+             \n\
+             # tar\n\
+             # Archiving utility.\n\
+             # More information: https://example.com/tar\n\
+             \n\
+             # List the contents:\n\
+             tar tf backup.tar\n\
+             \n\
+             # Create an archive:\n\
+             # TODO: replace {{target.tar}} {{file}}\n\
+             # tar cf {{target.tar}} {{file}}\n"
+        );
+    }
+}
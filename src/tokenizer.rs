@@ -1,6 +1,7 @@
 //! Code to tokenize a `BufRead` instance into an iterator of `LineType`s.
 
-use std::io::BufRead;
+use std::borrow::Cow;
+use std::io::{self, BufRead};
 
 use crate::types::LineType;
 use log::warn;
@@ -25,8 +26,14 @@ pub struct Tokenizer<R: BufRead> {
     first_line: bool,
     /// Buffer for the current line. Used internally.
     current_line: String,
+    /// Buffer for the raw bytes of the current line. Used internally.
+    current_bytes: Vec<u8>,
     /// The tldr page format.
     format: TldrFormat,
+    /// Whether invalid UTF-8 has been replaced in any line.
+    lossy: bool,
+    /// Whether trailing whitespace is trimmed from example code lines.
+    trim_code: bool,
 }
 
 impl<R> Tokenizer<R>
@@ -38,13 +45,59 @@ where
             reader,
             first_line: true,
             current_line: String::new(),
+            current_bytes: Vec::new(),
             format: TldrFormat::Undecided,
+            lossy: false,
+            trim_code: true,
         }
     }
 
-    pub fn next_token(&mut self) -> Option<LineType> {
+    /// Whether trailing whitespace is trimmed from example code lines (by
+    /// default, it is). Other lines are always trimmed.
+    pub fn with_trimmed_code(mut self, trim_code: bool) -> Self {
+        self.trim_code = trim_code;
+        self
+    }
+
+    /// Return the trailing whitespace of the code in the current line.
+    fn code_trailing_whitespace(&self) -> &str {
+        let line = self
+            .current_line
+            .trim_end_matches(|c| c == '\n' || c == '\r');
+        // In the original format, the code is enclosed in backticks
+        let code = match self.format {
+            TldrFormat::V1 => line.trim_end().strip_suffix('`').unwrap_or(line),
+            _ => line,
+        };
+        &code[code.trim_end().len()..]
+    }
+
+    /// Whether invalid UTF-8 has been replaced in any of the lines read so far.
+    pub fn is_lossy(&self) -> bool {
+        self.lossy
+    }
+
+    /// Read the next line into `current_line`.
+    ///
+    /// Invalid UTF-8 is replaced (with U+FFFD), so that a single broken line
+    /// doesn't prevent rendering the rest of the page.
+    fn read_line(&mut self) -> io::Result<usize> {
         self.current_line.clear();
-        let bytes_read = self.reader.read_line(&mut self.current_line);
+        self.current_bytes.clear();
+        let bytes_read = self.reader.read_until(b'\n', &mut self.current_bytes)?;
+        match String::from_utf8_lossy(&self.current_bytes) {
+            Cow::Borrowed(line) => self.current_line.push_str(line),
+            Cow::Owned(line) => {
+                warn!("Replacing invalid UTF-8 in line: {:?}", line);
+                self.lossy = true;
+                self.current_line = line;
+            }
+        }
+        Ok(bytes_read)
+    }
+
+    pub fn next_token(&mut self) -> Option<LineType> {
+        let bytes_read = self.read_line();
         match bytes_read {
             Ok(0) => None,
             Err(e) => {
@@ -56,8 +109,8 @@ where
                 if self.first_line && !self.current_line.starts_with('#') {
                     // It's the new format! Drop next line.
                     // (Hmm, is there a way to do this without an allocation?)
-                    let mut devnull = String::new();
-                    if let Err(e) = self.reader.read_line(&mut devnull) {
+                    let mut devnull = Vec::new();
+                    if let Err(e) = self.reader.read_until(b'\n', &mut devnull) {
                         warn!("Could not read line from token reader: {:?}", e);
                         return None;
                     }
@@ -75,10 +128,16 @@ where
                 }
 
                 // Convert line to a `LineType` instance
-                match self.format {
-                    TldrFormat::V1 => Some(LineType::from_v1(&self.current_line[..])),
-                    TldrFormat::V2 => Some(LineType::from(&self.current_line[..])),
+                let token = match self.format {
+                    TldrFormat::V1 => LineType::from_v1(&self.current_line[..]),
+                    TldrFormat::V2 => LineType::from(&self.current_line[..]),
                     TldrFormat::Undecided => panic!("Could not determine page format version"),
+                };
+                match token {
+                    LineType::ExampleCode(code) if !self.trim_code => Some(LineType::ExampleCode(
+                        code + self.code_trailing_whitespace(),
+                    )),
+                    token => Some(token),
                 }
             }
         }
@@ -100,6 +159,29 @@ mod test {
         assert_eq!(empty, LineType::Empty);
     }
 
+    #[test]
+    fn test_invalid_utf8() {
+        let input: &[u8] = b"# The Title\n\n- Some \xff example:\n\n`echo {{\xfe}}`\n";
+        let mut tokenizer = Tokenizer::new(input);
+        assert_eq!(
+            tokenizer.next_token().unwrap(),
+            LineType::Title("The Title".to_string())
+        );
+        assert!(!tokenizer.is_lossy());
+        assert_eq!(tokenizer.next_token().unwrap(), LineType::Empty);
+        assert_eq!(
+            tokenizer.next_token().unwrap(),
+            LineType::ExampleText("Some \u{fffd} example:".to_string())
+        );
+        assert!(tokenizer.is_lossy());
+        assert_eq!(tokenizer.next_token().unwrap(), LineType::Empty);
+        assert_eq!(
+            tokenizer.next_token().unwrap(),
+            LineType::ExampleCode("echo {{\u{fffd}}}".to_string())
+        );
+        assert_eq!(tokenizer.next_token(), None);
+    }
+
     #[test]
     fn test_first_line_new_format() {
         let input = "The Title\n=========\n\n";
@@ -109,4 +191,22 @@ mod test {
         let empty = tokenizer.next_token().unwrap();
         assert_eq!(empty, LineType::Empty);
     }
+
+    #[test]
+    fn test_code_trailing_whitespace() {
+        let v1 = "# trailing\n\n- Greet:\n\n`echo hi\t `  \n";
+        let v2 = "trailing\n========\n\nGreet:\n\n    echo hi\t \r\n";
+        for input in &[v1, v2] {
+            let code = |trim_code| {
+                let mut tokenizer = Tokenizer::new(input.as_bytes()).with_trimmed_code(trim_code);
+                std::iter::from_fn(|| tokenizer.next_token())
+                    .find(|token| matches!(token, LineType::ExampleCode(_)))
+            };
+            assert_eq!(code(true), Some(LineType::ExampleCode("echo hi".into())));
+            assert_eq!(
+                code(false),
+                Some(LineType::ExampleCode("echo hi\t ".into()))
+            );
+        }
+    }
 }
@@ -12,9 +12,43 @@ pub enum OsType {
     OsX,
     SunOs,
     Windows,
+    FreeBsd,
+    NetBsd,
+    OpenBsd,
+    /// Any platform, pages are looked up for all platforms (in the order of
+    /// `PLATFORMS`)
+    Any,
     Other,
 }
 
+impl OsType {
+    /// The platforms that pages can be looked up for.
+    pub const PLATFORMS: [Self; 7] = [
+        Self::Linux,
+        Self::OsX,
+        Self::SunOs,
+        Self::Windows,
+        Self::FreeBsd,
+        Self::NetBsd,
+        Self::OpenBsd,
+    ];
+
+    /// The name of the platform, as used by `--os` and the pages directories.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Linux => "linux",
+            Self::OsX => "osx",
+            Self::SunOs => "sunos",
+            Self::Windows => "windows",
+            Self::FreeBsd => "freebsd",
+            Self::NetBsd => "netbsd",
+            Self::OpenBsd => "openbsd",
+            Self::Any => "any",
+            Self::Other => "other",
+        }
+    }
+}
+
 impl fmt::Display for OsType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -22,6 +56,10 @@ impl fmt::Display for OsType {
             Self::OsX => write!(f, "macOS / BSD"),
             Self::SunOs => write!(f, "SunOS"),
             Self::Windows => write!(f, "Windows"),
+            Self::FreeBsd => write!(f, "FreeBSD"),
+            Self::NetBsd => write!(f, "NetBSD"),
+            Self::OpenBsd => write!(f, "OpenBSD"),
+            Self::Any => write!(f, "Any platform"),
             Self::Other => write!(f, "Unknown OS"),
         }
     }
@@ -35,13 +73,52 @@ pub enum ColorOptions {
     Never,
 }
 
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+/// The rendering of the placeholders of the example code, `--placeholders`.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlaceholderStyle {
+    /// As they are (e.g. `file`)
+    Named,
+    /// As numbered positional arguments (e.g. `$1`)
+    Positional,
+}
+
+/// The order of the commands of `--list`.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ListOrder {
+    /// Alphabetically
+    Name,
+    /// Most recently modified page first
+    Mtime,
+    /// By the platform the page is shown from (custom pages first, then in
+    /// search order), alphabetically within each platform
+    Platform,
+}
+
 #[derive(Debug, Eq, PartialEq)]
 pub enum LineType {
     Empty,
     Title(String),
     Description(String),
+    /// The `More information: <url>.` line of the description, with the URL
+    MoreInfo(String),
+    /// The ``See also: `a`, `b`.`` line of the description, with the commands
+    SeeAlso(Vec<String>),
+    /// The `Usage: <synopsis>` line of the description, with the synopsis
+    Synopsis(String),
     ExampleText(String),
     ExampleCode(String),
+    /// A `<!-- language: <name> -->` hint for the language of the following
+    /// example code (e.g. `powershell`)
+    CodeLanguage(String),
     Other(String),
 }
 
@@ -49,6 +126,9 @@ impl<'a> From<&'a str> for LineType {
     /// Convert a string slice to a `LineType`. Newlines and trailing whitespace are trimmed.
     fn from(line: &'a str) -> Self {
         let trimmed: &str = line.trim_end();
+        if let Some(language) = Self::code_language(trimmed) {
+            return language;
+        }
         let mut chars = trimmed.chars();
         match chars.next() {
             None => Self::Empty,
@@ -57,10 +137,8 @@ impl<'a> From<&'a str> for LineType {
                     .trim_start_matches(|chr: char| chr == '#' || chr.is_whitespace())
                     .into(),
             ),
-            Some('>') => Self::Description(
-                trimmed
-                    .trim_start_matches(|chr: char| chr == '>' || chr.is_whitespace())
-                    .into(),
+            Some('>') => Self::description(
+                trimmed.trim_start_matches(|chr: char| chr == '>' || chr.is_whitespace()),
             ),
             Some(' ') => Self::ExampleCode(trimmed.trim_start_matches(char::is_whitespace).into()),
             Some(_) => Self::ExampleText(trimmed.into()),
@@ -68,11 +146,85 @@ impl<'a> From<&'a str> for LineType {
     }
 }
 
+/// The prefix of the description line that links to more information.
+pub const MORE_INFO_PREFIX: &str = "More information:";
+
+/// The prefix of the description line that lists related commands.
+pub const SEE_ALSO_PREFIX: &str = "See also:";
+
+/// The prefix of the description line with the synopsis of the command.
+pub const SYNOPSIS_PREFIX: &str = "Usage:";
+
 impl LineType {
+    /// Return a description line, or a `MoreInfo` line if it has the standard
+    /// "More information:" prefix followed by a URL, or a `SeeAlso` line if it
+    /// has the "See also:" prefix followed by comma separated commands, or a
+    /// `Synopsis` line if it has the "Usage:" prefix.
+    fn description(text: &str) -> Self {
+        if let Some(commands) = Self::see_also(text) {
+            return Self::SeeAlso(commands);
+        }
+        if let Some(synopsis) = Self::synopsis(text) {
+            return Self::Synopsis(synopsis.into());
+        }
+        let url = text
+            .strip_prefix(MORE_INFO_PREFIX)
+            .map(|rest| {
+                rest.trim()
+                    .trim_end_matches('.')
+                    .trim_start_matches('<')
+                    .trim_end_matches('>')
+            })
+            .filter(|url| url.starts_with("https://") || url.starts_with("http://"));
+        match url {
+            Some(url) => Self::MoreInfo(url.into()),
+            None => Self::Description(text.into()),
+        }
+    }
+
+    /// Return the commands of a "See also:" line, in backticks or not.
+    fn see_also(text: &str) -> Option<Vec<String>> {
+        let commands: Vec<String> = text
+            .strip_prefix(SEE_ALSO_PREFIX)?
+            .trim()
+            .trim_end_matches('.')
+            .split(',')
+            .map(|command| command.trim().trim_matches('`').trim())
+            .filter(|command| !command.is_empty())
+            .map(String::from)
+            .collect();
+        if commands.is_empty() {
+            None
+        } else {
+            Some(commands)
+        }
+    }
+
+    /// Return the synopsis of a "Usage:" line, in backticks or not.
+    fn synopsis(text: &str) -> Option<&str> {
+        text.strip_prefix(SYNOPSIS_PREFIX)
+            .map(|rest| rest.trim().trim_matches('`').trim())
+            .filter(|synopsis| !synopsis.is_empty())
+    }
+
+    /// Return a `CodeLanguage` line if `line` is a language hint comment.
+    fn code_language(line: &str) -> Option<Self> {
+        line.trim()
+            .strip_prefix("<!--")
+            .and_then(|rest| rest.strip_suffix("-->"))
+            .and_then(|comment| comment.trim().strip_prefix("language:"))
+            .map(str::trim)
+            .filter(|language| !language.is_empty())
+            .map(|language| Self::CodeLanguage(language.into()))
+    }
+
     /// Support for old format.
     /// TODO: Remove once old format has been phased out!
     pub fn from_v1(line: &str) -> Self {
         let trimmed = line.trim();
+        if let Some(language) = Self::code_language(trimmed) {
+            return language;
+        }
         let mut chars = trimmed.chars();
         match chars.next() {
             None => Self::Empty,
@@ -81,10 +233,8 @@ impl LineType {
                     .trim_start_matches(|chr: char| chr == '#' || chr.is_whitespace())
                     .into(),
             ),
-            Some('>') => Self::Description(
-                trimmed
-                    .trim_start_matches(|chr: char| chr == '>' || chr.is_whitespace())
-                    .into(),
+            Some('>') => Self::description(
+                trimmed.trim_start_matches(|chr: char| chr == '>' || chr.is_whitespace()),
             ),
             Some('-') => Self::ExampleText(
                 trimmed
@@ -108,6 +258,8 @@ pub enum PathSource {
     OsConvention,
     /// Env variable (TEALDEER_*)
     EnvVar,
+    /// Command line argument
+    Cli,
 
     #[allow(dead_code)] // Waiting for Pull Request #141
     /// Config file variable
@@ -122,6 +274,7 @@ impl fmt::Display for PathSource {
             match self {
                 Self::OsConvention => "OS convention",
                 Self::EnvVar => "env variable",
+                Self::Cli => "command line argument",
                 Self::ConfigVar => "config file variable",
             }
         )
@@ -144,6 +297,30 @@ mod test {
             LineType::from("> tis a description \n"),
             LineType::Description("tis a description".into())
         );
+        assert_eq!(
+            LineType::from("> More information: <https://example.com/tar>."),
+            LineType::MoreInfo("https://example.com/tar".into())
+        );
+        assert_eq!(
+            LineType::from("> More information: see the manual."),
+            LineType::Description("More information: see the manual.".into())
+        );
+        assert_eq!(
+            LineType::from("> See also: `lsof`, `ss`, netstat."),
+            LineType::SeeAlso(vec!["lsof".into(), "ss".into(), "netstat".into()])
+        );
+        assert_eq!(
+            LineType::from("> See also:"),
+            LineType::Description("See also:".into())
+        );
+        assert_eq!(
+            LineType::from("> Usage: `tar [options] {{file}}`"),
+            LineType::Synopsis("tar [options] {{file}}".into())
+        );
+        assert_eq!(
+            LineType::from("> Usage:"),
+            LineType::Description("Usage:".into())
+        );
         assert_eq!(
             LineType::from("some command "),
             LineType::ExampleText("some command".into())
@@ -152,5 +329,17 @@ mod test {
             LineType::from("    $ cargo run "),
             LineType::ExampleCode("$ cargo run".into())
         );
+        assert_eq!(
+            LineType::from("<!-- language: powershell -->"),
+            LineType::CodeLanguage("powershell".into())
+        );
+        assert_eq!(
+            LineType::from_v1("<!--language:cmd-->"),
+            LineType::CodeLanguage("cmd".into())
+        );
+        assert_eq!(
+            LineType::from_v1("<!-- a comment -->"),
+            LineType::Other("<!-- a comment -->".into())
+        );
     }
 }
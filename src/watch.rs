@@ -0,0 +1,203 @@
+//! A live preview for page authors, used with `--render <file> --watch`.
+//!
+//! The file is polled for changes (of its content), which works the same on
+//! every platform and needs no notification backend. On Unix, the preview is
+//! shown on the alternate screen and Ctrl-C ends it cleanly, restoring the
+//! screen as it was before.
+
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::Hasher;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+/// How often the file is checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Clears the screen and moves the cursor to the top left corner.
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+
+/// Switches to the alternate screen, and back to the original one.
+const ENTER_ALTERNATE_SCREEN: &str = "\x1b[?1049h";
+const LEAVE_ALTERNATE_SCREEN: &str = "\x1b[?1049l";
+
+/// Set by the signal handler on Ctrl-C (or SIGTERM).
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// The state of the watched file, a change of it triggers a render.
+#[derive(Debug, PartialEq, Eq)]
+enum FileState {
+    Missing,
+    /// The hash of the content, so that edits that keep the size (and, on a
+    /// coarse clock, the modification time) are noticed as well
+    Present(u64),
+}
+
+impl FileState {
+    fn of(path: &Path) -> Self {
+        match fs::read(path) {
+            Ok(content) => {
+                let mut hasher = DefaultHasher::new();
+                hasher.write(&content);
+                Self::Present(hasher.finish())
+            }
+            Err(_) => Self::Missing,
+        }
+    }
+}
+
+#[cfg(unix)]
+extern "C" fn on_interrupt(_signal: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/// Stop watching on Ctrl-C and SIGTERM instead of ending the process, return
+/// whether that is supported on this platform.
+#[cfg(unix)]
+fn handle_interrupts() -> bool {
+    let handler = on_interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    unsafe {
+        libc::signal(libc::SIGINT, handler) != libc::SIG_ERR
+            && libc::signal(libc::SIGTERM, handler) != libc::SIG_ERR
+    }
+}
+
+#[cfg(not(unix))]
+fn handle_interrupts() -> bool {
+    false
+}
+
+/// Clear the screen and call `render` whenever the file at `path` changes,
+/// until the process is interrupted (e.g. with Ctrl-C).
+///
+/// While the file is missing (e.g. while an editor replaces it), a message is
+/// shown until it reappears. Errors of `render` are shown instead of the page,
+/// so that watching continues until the page is fixed.
+pub fn watch<F>(path: &Path, render: F) -> Result<(), String>
+where
+    F: FnMut() -> Result<(), String>,
+{
+    // Without a clean exit, the alternate screen would stay active
+    let alternate_screen = handle_interrupts();
+    watch_until(path, &mut io::stdout(), alternate_screen, render, || {
+        INTERRUPTED.load(Ordering::SeqCst)
+    })
+    .map_err(|e| format!("Could not write output: {}", e))
+}
+
+/// Watch the file at `path` (see `watch`) until `interrupted` returns true.
+fn watch_until<W, F, I>(
+    path: &Path,
+    out: &mut W,
+    alternate_screen: bool,
+    mut render: F,
+    interrupted: I,
+) -> io::Result<()>
+where
+    W: Write,
+    F: FnMut() -> Result<(), String>,
+    I: Fn() -> bool,
+{
+    if alternate_screen {
+        write!(out, "{}", ENTER_ALTERNATE_SCREEN)?;
+    }
+    let mut last_state = None;
+    while !interrupted() {
+        let state = FileState::of(path);
+        if last_state.as_ref() != Some(&state) {
+            write!(out, "{}", CLEAR_SCREEN)?;
+            if state == FileState::Missing {
+                writeln!(
+                    out,
+                    "{} does not exist (anymore), waiting for it to reappear...",
+                    path.display()
+                )?;
+            } else {
+                out.flush()?;
+                if let Err(msg) = render() {
+                    writeln!(out, "{}", msg)?;
+                }
+            }
+            out.flush()?;
+            last_state = Some(state);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+    if alternate_screen {
+        write!(out, "{}", LEAVE_ALTERNATE_SCREEN)?;
+    }
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        watch_until, FileState, CLEAR_SCREEN, ENTER_ALTERNATE_SCREEN, LEAVE_ALTERNATE_SCREEN,
+    };
+    use std::cell::Cell;
+    use std::fs;
+
+    #[test]
+    fn test_file_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("page.md");
+        assert_eq!(FileState::of(&path), FileState::Missing);
+
+        fs::write(&path, "# page\n").unwrap();
+        let state = FileState::of(&path);
+        assert_ne!(state, FileState::Missing);
+        assert_eq!(FileState::of(&path), state);
+
+        fs::write(&path, "# page\n\n> Changed.\n").unwrap();
+        assert_ne!(FileState::of(&path), state);
+
+        // An edit of the same size
+        let state = FileState::of(&path);
+        fs::write(&path, "# page\n\n> Chanced.\n").unwrap();
+        assert_ne!(FileState::of(&path), state);
+    }
+
+    #[test]
+    fn test_watch_until_interrupted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("page.md");
+        fs::write(&path, "# page\n").unwrap();
+
+        // Interrupted after the first render
+        let renders = Cell::new(0);
+        let mut out = Vec::new();
+        watch_until(
+            &path,
+            &mut out,
+            true,
+            || {
+                renders.set(renders.get() + 1);
+                Err("Invalid page".into())
+            },
+            || renders.get() > 0,
+        )
+        .unwrap();
+        assert_eq!(renders.get(), 1);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!(
+                "{}{}Invalid page\n{}",
+                ENTER_ALTERNATE_SCREEN, CLEAR_SCREEN, LEAVE_ALTERNATE_SCREEN
+            )
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_interrupt() {
+        use super::{handle_interrupts, INTERRUPTED};
+        use std::sync::atomic::Ordering;
+
+        assert!(handle_interrupts());
+        unsafe { libc::raise(libc::SIGINT) };
+        assert!(INTERRUPTED.load(Ordering::SeqCst));
+    }
+}
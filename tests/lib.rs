@@ -1,13 +1,13 @@
 //! Integration tests.
 
-use std::fs::{create_dir_all, File};
+use std::fs::{self, create_dir_all, File};
 use std::io::Write;
 use std::process::Command;
 use std::time::{Duration, SystemTime};
 
 use assert_cmd::prelude::*;
 use predicates::boolean::PredicateBooleanExt;
-use predicates::prelude::predicate::str::{contains, is_empty, similar};
+use predicates::prelude::predicate::str::{contains, ends_with, is_empty, similar};
 use tempfile::{Builder, TempDir};
 
 struct TestEnv {
@@ -114,6 +114,7 @@ impl TestEnv {
             "TEALDEER_CONFIG_DIR",
             self.config_dir.path().to_str().unwrap(),
         );
+        cmd.env_remove("TEALDEER_CONFIG");
         cmd
     }
 }
@@ -128,6 +129,83 @@ fn test_missing_cache() {
         .stderr(contains("Cache not found. Please run `tldr --update`."));
 }
 
+#[cfg(not(target_os = "windows"))]
+#[test]
+fn test_on_not_found_command() {
+    let testenv = TestEnv::new();
+
+    testenv.add_entry("which", include_str!("which-markdown.expected"));
+    testenv.write_config(
+        "[display]\non_not_found_command = 'echo \"Creating page $TEALDEER_COMMAND\"'\n",
+    );
+
+    testenv
+        .command()
+        .args(&["which"])
+        .assert()
+        .success()
+        .stdout(contains("Creating page").not());
+
+    testenv
+        .command()
+        .args(&["nonexistent"])
+        .assert()
+        .code(1)
+        .stdout(contains("Creating page nonexistent"));
+
+    // A failing hook doesn't change the exit code
+    testenv.write_config("[display]\non_not_found_command = 'exit 3'\n");
+
+    testenv
+        .command()
+        .args(&["nonexistent"])
+        .assert()
+        .code(1)
+        .stderr(contains("Warning: on_not_found_command failed"));
+}
+
+#[test]
+fn test_exit_codes() {
+    let testenv = TestEnv::new();
+
+    // Cache missing
+    testenv.command().args(&["which"]).assert().code(3);
+
+    testenv.add_entry("which", include_str!("which-markdown.expected"));
+
+    testenv.command().args(&["which"]).assert().code(0);
+    testenv.command().args(&["nonexistent"]).assert().code(1);
+    testenv
+        .command()
+        .args(&["--diff", "nonexistent"])
+        .assert()
+        .code(1)
+        .stderr(contains("Page nonexistent not found"));
+    testenv
+        .command()
+        .args(&["--language", "not a language", "which"])
+        .assert()
+        .code(2);
+    testenv
+        .command()
+        .args(&["--not-an-option"])
+        .assert()
+        .code(2);
+
+    // Config error
+    testenv.write_config("[display]\ncompact = 'yes'\n");
+    testenv.command().args(&["which"]).assert().code(4);
+    testenv.write_config("");
+
+    // Update error, the proxy is unreachable
+    testenv
+        .command()
+        .args(&["--update"])
+        .env("HTTPS_PROXY", "http://127.0.0.1:9")
+        .assert()
+        .code(5);
+}
+
 #[test]
 fn test_update_cache() {
     let testenv = TestEnv::new();
@@ -186,6 +264,31 @@ fn test_quiet_failures() {
         .stdout(is_empty());
 }
 
+#[test]
+fn test_max_cache_age() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("which", include_str!("which-markdown.expected"));
+    testenv.write_config("[updates]\nmax_cache_age_hours = 24\n");
+
+    testenv.command().args(&["which"]).assert().success();
+
+    filetime::set_file_mtime(
+        testenv.cache_dir.path().join("tldr-master"),
+        filetime::FileTime::from_system_time(SystemTime::now() - Duration::from_secs(25 * 3600)),
+    )
+    .unwrap();
+
+    testenv
+        .command()
+        .args(&["which", "--quiet"])
+        .assert()
+        .code(3)
+        .stdout(is_empty())
+        .stderr(contains(
+            "The cache hasn't been updated for more than 24 hours",
+        ));
+}
+
 #[test]
 fn test_quiet_old_cache() {
     let testenv = TestEnv::new();
@@ -219,249 +322,2486 @@ fn test_quiet_old_cache() {
 }
 
 #[test]
-fn test_setup_seed_config() {
+/// Without a terminal, a stale cache only produces a warning, even if prompting is enabled.
+fn test_stale_cache_prompt_non_interactive() {
     let testenv = TestEnv::new();
 
+    testenv.add_entry("which", include_str!("which-markdown.expected"));
+    testenv.write_config("[updates]\nprompt_on_stale = true\n");
+
+    filetime::set_file_mtime(
+        testenv.cache_dir.path().join("tldr-master"),
+        filetime::FileTime::from_unix_time(1, 0),
+    )
+    .unwrap();
+
     testenv
         .command()
-        .args(&["--seed-config"])
+        .args(&["which"])
         .assert()
         .success()
-        .stderr(contains("Successfully created seed config file here"));
+        .stderr(
+            contains("The cache hasn't been updated for more than ")
+                .and(contains("Update now?").not()),
+        );
 }
 
 #[test]
-fn test_show_paths() {
+fn test_stale_exit_code() {
     let testenv = TestEnv::new();
 
+    testenv.add_entry("which", include_str!("which-markdown.expected"));
+    testenv.write_config("[updates]\nstale_exit_code = 10\n");
+
+    testenv.command().args(&["which"]).assert().success();
+
+    filetime::set_file_mtime(
+        testenv.cache_dir.path().join("tldr-master"),
+        filetime::FileTime::from_unix_time(1, 0),
+    )
+    .unwrap();
+
     testenv
         .command()
-        .args(&["--show-paths"])
+        .args(&["which"])
         .assert()
-        .success()
-        .stdout(contains(format!(
-            "Config dir:  {}",
-            testenv.config_dir.path().to_str().unwrap(),
-        )))
-        .stdout(contains(format!(
-            "Config path: {}",
-            testenv
-                .config_dir
-                .path()
-                .join("config.toml")
-                .to_str()
-                .unwrap(),
-        )))
-        .stdout(contains(format!(
-            "Cache dir:   {}",
-            testenv.cache_dir.path().to_str().unwrap(),
-        )))
-        .stdout(contains(format!(
-            "Pages dir:   {}",
-            testenv
-                .cache_dir
-                .path()
-                .join("tldr-master")
-                .to_str()
-                .unwrap(),
-        )));
-}
-
-#[test]
-fn test_os_specific_page() {
-    let testenv = TestEnv::new();
-
-    testenv.add_os_entry("sunos", "truss", "contents");
+        .code(10)
+        .stdout(contains("Locate a program"));
 
     testenv
         .command()
-        .args(&["--os", "sunos", "truss"])
+        .args(&["--quiet", "which"])
         .assert()
-        .success();
+        .code(10)
+        .stderr(is_empty());
 }
 
 #[test]
-fn test_markdown_rendering() {
+fn test_cache_format_version() {
     let testenv = TestEnv::new();
+    testenv.add_entry("which", "# which\n\n> Locate a program.");
+    let version_file = testenv.cache_dir.path().join("format-version");
 
-    testenv.add_entry("which", include_str!("which-markdown.expected"));
+    // Caches without a version marker are migrated in place
+    testenv.command().args(&["which"]).assert().success();
+    assert_eq!(fs::read_to_string(&version_file).unwrap(), "1\n");
 
-    let expected = include_str!("which-markdown.expected");
+    // Caches with an unknown format are not used
+    fs::write(&version_file, "99\n").unwrap();
     testenv
         .command()
-        .args(&["-m", "which"])
+        .args(&["which"])
         .assert()
-        .success()
-        .stdout(similar(expected));
+        .failure()
+        .code(3)
+        .stderr(contains("format version 99").and(contains("Please run `tldr --update`")));
 }
 
-fn _test_correct_rendering(
-    input_file: &str,
-    filename: &str,
-    expected: &'static str,
-    color_option: &str,
-) {
+#[test]
+fn test_strict_config() {
     let testenv = TestEnv::new();
+    testenv.add_entry("which", "# which\n\n> Locate a program.");
+    testenv.write_config("[display]\ncompcat = true\n");
 
-    // Create input file
-    let file_path = testenv.input_dir.path().join(filename);
-    println!("Testfile path: {:?}", &file_path);
-    let mut file = File::create(&file_path).unwrap();
-    file.write_all(input_file.as_bytes()).unwrap();
+    // Unknown keys are ignored by default
+    testenv.command().args(&["which"]).assert().success();
 
     testenv
         .command()
-        .args(&["--color", color_option, "-f", &file_path.to_str().unwrap()])
+        .args(&["--strict-config", "which"])
         .assert()
-        .success()
-        .stdout(similar(expected));
-}
-
-/// An end-to-end integration test for direct file rendering (v1 syntax).
-#[test]
-fn test_correct_rendering_v1() {
-    _test_correct_rendering(
-        include_str!("inkscape-v1.md"),
-        "inkscape-v1.md",
-        include_str!("inkscape-default.expected"),
-        "always",
-    );
-}
-
-/// An end-to-end integration test for direct file rendering (v2 syntax).
-#[test]
-fn test_correct_rendering_v2() {
-    _test_correct_rendering(
-        include_str!("inkscape-v2.md"),
-        "inkscape-v2.md",
-        include_str!("inkscape-default.expected"),
-        "always",
-    );
-}
-
-#[test]
-/// An end-to-end integration test for direct file rendering with the `--color auto` option. This
-/// will not use styling since output is not stdout.
-fn test_rendering_color_auto() {
-    _test_correct_rendering(
-        include_str!("inkscape-v2.md"),
-        "inkscape-v2.md",
-        include_str!("inkscape-default-no-color.expected"),
-        "auto",
-    );
-}
+        .failure()
+        .code(4)
+        .stderr(contains("Unknown config key `display.compcat` (line 2)"));
 
-#[test]
-/// An end-to-end integration test for direct file rendering with the `--color never` option.
-fn test_rendering_color_never() {
-    _test_correct_rendering(
-        include_str!("inkscape-v2.md"),
-        "inkscape-v2.md",
-        include_str!("inkscape-default-no-color.expected"),
-        "never",
-    );
+    testenv.write_config("[display]\nstrict_config = true\ncompcat = true\n");
+    testenv
+        .command()
+        .args(&["which"])
+        .assert()
+        .failure()
+        .code(4)
+        .stderr(contains("Unknown config key `display.compcat` (line 3)"));
 }
 
-/// An end-to-end integration test for rendering with custom syntax config.
 #[test]
-fn test_correct_rendering_with_config() {
+fn test_invalid_utf8_page() {
     let testenv = TestEnv::new();
+    testenv.add_entry("which", "# which\n\n> Locate a program.");
+    let dir = testenv.cache_dir.path().join("tldr-master/pages/common");
+    fs::write(
+        dir.join("broken.md"),
+        &include_bytes!("invalid-utf8.md")[..],
+    )
+    .unwrap();
 
-    // Setup config file
-    // TODO should be config::CONFIG_FILE_NAME
-    let config_file_path = testenv.config_dir.path().join("config.toml");
-    println!("Config path: {:?}", &config_file_path);
-
-    let mut config_file = File::create(&config_file_path).unwrap();
-    config_file
-        .write_all(include_str!("config.toml").as_bytes())
-        .unwrap();
-
-    // Create input file
-    let file_path = testenv.input_dir.path().join("inkscape-v2.md");
-    println!("Testfile path: {:?}", &file_path);
-
-    let mut file = File::create(&file_path).unwrap();
-    file.write_all(include_str!("inkscape-v2.md").as_bytes())
-        .unwrap();
-
-    // Load expected output
-    let expected = include_str!("inkscape-with-config.expected");
+    for flag in &["--no-styling", "--color=never", "--markdown"] {
+        testenv
+            .command()
+            .args(&[flag, "broken"])
+            .assert()
+            .success()
+            .stdout(contains("Print some \u{fffd} text:"))
+            .stderr(contains("broken.md is not valid UTF-8"));
+    }
 
     testenv
         .command()
-        .args(&["--color", "always", "-f", &file_path.to_str().unwrap()])
+        .args(&["--list"])
         .assert()
         .success()
-        .stdout(similar(expected));
+        .stdout(similar("broken\nwhich\n"));
 }
 
 #[test]
-fn test_spaces_find_command() {
+fn test_max_lines() {
     let testenv = TestEnv::new();
+    testenv.add_entry("which", include_str!("which-markdown.expected"));
 
     testenv
         .command()
-        .args(&["--update"])
+        .args(&["--color=never", "--max-lines", "3", "which"])
         .assert()
         .success()
-        .stderr(contains("Successfully updated cache."));
-
+        .stdout(similar(
+            "\n  Locate a program in the user's path.\n\n\u{2026} (truncated, 8 more lines)\n",
+        ));
     testenv
         .command()
-        .args(&["git", "checkout"])
+        .args(&["--color=never", "--max-lines", "3", "--quiet", "which"])
         .assert()
-        .success();
+        .success()
+        .stdout(similar("\n  Locate a program in the user's path.\n\n"));
+    testenv
+        .command()
+        .args(&["--format", "json", "--max-lines", "1", "which"])
+        .assert()
+        .success()
+        .stdout(contains("\"examples\""));
 }
 
 #[test]
-fn test_pager_flag_enable() {
+/// Trailing whitespace of the example code is not rendered by default.
+fn test_trailing_whitespace() {
     let testenv = TestEnv::new();
+    testenv.add_entry("trailing", include_str!("trailing-whitespace.md"));
+
+    let output = testenv
+        .command()
+        .args(&["--color=never", "trailing"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("    echo \"Hello\"\n"), "{}", stdout);
+    assert!(stdout.contains("    echo \"Hi\"\n"), "{}", stdout);
 
     testenv
         .command()
-        .args(&["--update"])
+        .args(&["--oneline", "trailing"])
         .assert()
         .success()
-        .stderr(contains("Successfully updated cache."));
+        .stdout(similar("echo \"Hello\""));
+    testenv
+        .command()
+        .args(&["--format", "json", "trailing"])
+        .assert()
+        .success()
+        .stdout(contains(r#""command": "echo \"Hi\"","#));
 
+    // Kept if configured
+    testenv.write_config("[display]\ntrim_example_whitespace = false\n");
+    let output = testenv
+        .command()
+        .args(&["--color=never", "trailing"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("    echo \"Hello\"   \n"), "{}", stdout);
+    assert!(stdout.contains("    echo \"Hi\"\t\n"), "{}", stdout);
     testenv
         .command()
-        .args(&["--pager", "which"])
+        .args(&["--oneline", "trailing"])
         .assert()
-        .success();
+        .success()
+        .stdout(similar("echo \"Hello\"   "));
+    testenv
+        .command()
+        .args(&["--format", "json", "trailing"])
+        .assert()
+        .success()
+        .stdout(contains(r#""command": "echo \"Hi\"\t","#));
 }
 
 #[test]
-fn test_list_flag_rendering() {
+fn test_setup_seed_config() {
     let testenv = TestEnv::new();
 
     testenv
         .command()
-        .args(&["--list"])
+        .args(&["--seed-config"])
+        .assert()
+        .success()
+        .stderr(contains("Successfully created seed config file here"));
+    let config_file_path = testenv.config_dir.path().join("config.toml");
+    assert!(config_file_path.is_file());
+
+    // An existing config is only overwritten with --force
+    testenv.write_config("[display]\ncompact = true\n");
+    testenv
+        .command()
+        .args(&["--seed-config"])
+        .assert()
+        .failure()
+        .code(4)
+        .stderr(contains("Use --force to overwrite it"));
+    assert!(fs::read_to_string(&config_file_path)
+        .unwrap()
+        .contains("compact = true"));
+    testenv
+        .command()
+        .args(&["--seed-config", "--force"])
+        .assert()
+        .success()
+        .stderr(contains("Successfully created seed config file here"));
+    assert!(fs::read_to_string(&config_file_path)
+        .unwrap()
+        .contains("compact = false"));
+}
+
+#[test]
+fn test_seed_config_template() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("inkscape-v2", include_str!("inkscape-v2.md"));
+    let config_file_path = testenv.config_dir.path().join("config.toml");
+
+    testenv
+        .command()
+        .args(&["--seed-config"])
+        .assert()
+        .success();
+    let template = fs::read_to_string(&config_file_path).unwrap();
+    assert!(template.contains("## Omit the empty lines of a page\n# compact = false\n"));
+
+    // The template renders pages like the default config
+    testenv
+        .command()
+        .args(&["--color", "always", "inkscape-v2"])
+        .assert()
+        .success()
+        .stdout(similar(format!(
+            "{}\x1b[0m",
+            include_str!("inkscape-default.expected")
+        )));
+
+    testenv
+        .command()
+        .args(&["--seed-config", "--minimal", "--force"])
+        .assert()
+        .success();
+    let minimal = fs::read_to_string(&config_file_path).unwrap();
+    assert!(minimal.contains("[style.command_name]"));
+    assert!(!minimal.contains('#'));
+    assert!(!minimal.contains("user_agent"));
+}
+
+#[test]
+fn test_setup_seed_config_dir() {
+    let testenv = TestEnv::new();
+    let other_dir = TempDir::new().expect("Couldn't create tempdir");
+    let config_dir = other_dir.path().join("nested");
+
+    // --config-dir takes precedence over TEALDEER_CONFIG_DIR
+    testenv
+        .command()
+        .args(&["--seed-config", "--config-dir"])
+        .arg(&config_dir)
+        .assert()
+        .success()
+        .stderr(contains(config_dir.join("config.toml").to_str().unwrap()));
+    assert!(config_dir.join("config.toml").is_file());
+    assert!(!testenv.config_dir.path().join("config.toml").exists());
+
+    // The same config is loaded again
+    testenv
+        .command()
+        .args(&["--show-paths", "--config-dir"])
+        .arg(&config_dir)
+        .assert()
+        .success()
+        .stdout(contains(format!(
+            "Config path: {}",
+            config_dir.join("config.toml").to_str().unwrap()
+        )));
+}
+
+#[test]
+fn test_show_paths() {
+    let testenv = TestEnv::new();
+
+    testenv
+        .command()
+        .args(&["--show-paths"])
+        .assert()
+        .success()
+        .stdout(contains(format!(
+            "Config dir:  {}",
+            testenv.config_dir.path().to_str().unwrap(),
+        )))
+        .stdout(contains(format!(
+            "Config path: {}",
+            testenv
+                .config_dir
+                .path()
+                .join("config.toml")
+                .to_str()
+                .unwrap(),
+        )))
+        .stdout(contains(format!(
+            "Cache dir:   {}",
+            testenv.cache_dir.path().to_str().unwrap(),
+        )))
+        .stdout(contains("format version 1)"))
+        .stdout(contains(format!(
+            "Pages dir:   {}",
+            testenv
+                .cache_dir
+                .path()
+                .join("tldr-master")
+                .to_str()
+                .unwrap(),
+        )));
+}
+
+#[test]
+fn test_custom_config_file() {
+    let testenv = TestEnv::new();
+
+    let config_file_path = testenv.input_dir.path().join("custom.toml");
+    let mut config_file = File::create(&config_file_path).unwrap();
+    config_file
+        .write_all(b"[display]\ncompact = true\n")
+        .unwrap();
+
+    testenv
+        .command()
+        .args(&[
+            "--config",
+            config_file_path.to_str().unwrap(),
+            "--show-paths",
+        ])
+        .assert()
+        .success()
+        .stdout(contains(format!(
+            "Config path: {}",
+            config_file_path.to_str().unwrap(),
+        )));
+
+    let file_path = testenv.input_dir.path().join("inkscape-v2.md");
+    let mut file = File::create(&file_path).unwrap();
+    file.write_all(include_str!("inkscape-v2.md").as_bytes())
+        .unwrap();
+
+    testenv
+        .command()
+        .args(&[
+            "--config",
+            config_file_path.to_str().unwrap(),
+            "--color",
+            "never",
+            "-f",
+            file_path.to_str().unwrap(),
+        ])
+        .assert()
+        .success()
+        .stdout(contains("\n\n  ").not());
+}
+
+#[test]
+fn test_missing_custom_config_file() {
+    let testenv = TestEnv::new();
+
+    let config_file_path = testenv.input_dir.path().join("missing.toml");
+
+    testenv
+        .command()
+        .args(&["--config", config_file_path.to_str().unwrap(), "tar"])
+        .assert()
+        .failure()
+        .stderr(contains("does not exist or is not a file"));
+}
+
+#[test]
+fn test_config_file_env() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("tar", "# tar\n\n> Archiving utility.");
+    testenv.write_config("[display]\nfooter = 'from the config dir'\n");
+
+    let config_file_path = testenv.input_dir.path().join("mounted.toml");
+    fs::write(&config_file_path, "[display]\nfooter = 'from the file'\n").unwrap();
+
+    // TEALDEER_CONFIG takes precedence over TEALDEER_CONFIG_DIR
+    testenv
+        .command()
+        .args(&["--config-path"])
+        .env("TEALDEER_CONFIG", &config_file_path)
+        .assert()
+        .success()
+        .stdout(contains(format!(
+            "Config path is: {}",
+            config_file_path.to_str().unwrap()
+        )));
+    testenv
+        .command()
+        .args(&["tar"])
+        .env("TEALDEER_CONFIG", &config_file_path)
+        .assert()
+        .success()
+        .stdout(contains("from the file"));
+
+    // But not over --config-dir
+    testenv
+        .command()
+        .args(&["--config-dir"])
+        .arg(testenv.config_dir.path())
+        .arg("tar")
+        .env("TEALDEER_CONFIG", &config_file_path)
+        .assert()
+        .success()
+        .stdout(contains("from the config dir"));
+
+    // Like with --config, the file must exist
+    testenv
+        .command()
+        .args(&["tar"])
+        .env(
+            "TEALDEER_CONFIG",
+            testenv.input_dir.path().join("missing.toml"),
+        )
+        .assert()
+        .failure()
+        .stderr(contains("does not exist or is not a file"));
+}
+
+#[test]
+fn test_os_specific_page() {
+    let testenv = TestEnv::new();
+
+    testenv.add_os_entry("sunos", "truss", "contents");
+
+    testenv
+        .command()
+        .args(&["--os", "sunos", "truss"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_any_os() {
+    let testenv = TestEnv::new();
+
+    testenv.add_os_entry("sunos", "truss", "# truss\n\n> SunOS truss.\n");
+    testenv.add_os_entry("osx", "ip", "# ip\n\n> macOS ip.\n");
+    testenv.add_os_entry("windows", "ip", "# ip\n\n> Windows ip.\n");
+    testenv.add_entry("tar", "# tar\n\n> Common tar.\n");
+
+    testenv
+        .command()
+        .args(&["--os", "linux", "truss"])
+        .assert()
+        .code(1);
+    testenv
+        .command()
+        .args(&["--os", "any", "truss"])
+        .assert()
+        .success()
+        .stdout(contains("SunOS truss."));
+    // Platforms are searched in a fixed order, then common
+    testenv
+        .command()
+        .args(&["--os", "any", "ip"])
+        .assert()
+        .success()
+        .stdout(contains("macOS ip."));
+    testenv
+        .command()
+        .args(&["--os", "any", "--verbose", "--verbose", "tar"])
+        .assert()
+        .success()
+        .stdout(contains("Common tar."))
+        .stderr(contains("Found page tar.md in common"));
+}
+
+#[test]
+fn test_platform_order() {
+    let testenv = TestEnv::new();
+
+    testenv.add_os_entry("osx", "ip", "# ip\n\n> macOS ip.\n");
+    testenv.add_os_entry("windows", "ip", "# ip\n\n> Windows ip.\n");
+    testenv.add_entry("tar", "# tar\n\n> Common tar.\n");
+    testenv.add_os_entry("windows", "tar", "# tar\n\n> Windows tar.\n");
+    testenv.write_config("[directories]\nplatform_order = [\"common\", \"windows\"]\n");
+
+    testenv
+        .command()
+        .arg("ip")
+        .assert()
+        .success()
+        .stdout(contains("Windows ip."));
+    testenv
+        .command()
+        .arg("tar")
+        .assert()
+        .success()
+        .stdout(contains("Common tar."));
+    testenv
+        .command()
+        .args(&["--no-common", "tar"])
+        .assert()
+        .success()
+        .stdout(contains("Windows tar."));
+
+    // An explicit platform replaces the order
+    testenv
+        .command()
+        .args(&["--os", "osx", "ip"])
+        .assert()
+        .success()
+        .stdout(contains("macOS ip."));
+
+    testenv.write_config("[directories]\nplatform_order = [\"beos\"]\n");
+    testenv
+        .command()
+        .arg("ip")
+        .assert()
+        .code(4)
+        .stderr(contains("Invalid platform \"beos\" in platform_order"));
+}
+
+#[test]
+fn test_command_config() {
+    let testenv = TestEnv::new();
+
+    testenv.add_os_entry("linux", "git", "# git\n\n> Linux git.\n");
+    testenv.add_os_entry("osx", "git", "# git\n\n> macOS git.\n");
+    let de_dir = testenv.cache_dir.path().join("tldr-master/pages.de/common");
+    create_dir_all(&de_dir).unwrap();
+    fs::write(de_dir.join("tar.md"), "# tar\n\n> Deutsch.\n").unwrap();
+    testenv.add_entry("tar", "# tar\n\n> English.\n");
+    testenv.write_config("[commands.git]\nplatform = 'osx'\n\n[commands.tar]\nlanguage = 'de'\n");
+
+    testenv
+        .command()
+        .args(&["--os", "linux", "git"])
+        .assert()
+        .success()
+        .stdout(contains("Linux git."));
+    testenv
+        .command()
+        .args(&["git"])
+        .assert()
+        .success()
+        .stdout(contains("macOS git."));
+
+    testenv
+        .command()
+        .args(&["tar"])
+        .assert()
+        .success()
+        .stdout(contains("Deutsch."));
+    testenv
+        .command()
+        .args(&["--language", "en", "tar"])
+        .assert()
+        .success()
+        .stdout(contains("English."));
+}
+
+#[test]
+fn test_resolution_caption() {
+    let testenv = TestEnv::new();
+
+    testenv.add_entry("tar", "# tar\n\n> English.\n");
+    testenv.add_os_entry("linux", "ip", "# ip\n\n> Linux ip.\n");
+    testenv.write_config("[display]\nshow_resolution_caption = true\n");
+
+    testenv
+        .command()
+        .args(&["--color", "always", "--language", "de", "tar"])
+        .assert()
+        .success()
+        .stdout(contains("\x1b[2m(en, common)\x1b[0m"));
+    testenv
+        .command()
+        .args(&["--color", "always", "--os", "linux", "ip"])
+        .assert()
+        .success()
+        .stdout(contains("(en, linux)"));
+
+    // Not shown in unstyled or quiet mode
+    testenv
+        .command()
+        .args(&["--color", "never", "tar"])
+        .assert()
+        .success()
+        .stdout(contains("(en, common)").not());
+    testenv
+        .command()
+        .args(&["--color", "always", "--quiet", "tar"])
+        .assert()
+        .success()
+        .stdout(contains("(en, common)").not());
+}
+
+#[test]
+fn test_invalid_language_code() {
+    let testenv = TestEnv::new();
+
+    testenv.add_entry("which", include_str!("which-markdown.expected"));
+
+    testenv
+        .command()
+        .args(&["--language", "de,french", "which"])
+        .assert()
+        .failure()
+        .stderr(contains("Invalid language code \"french\""));
+}
+
+#[test]
+fn test_bsd_platform_fallback() {
+    let testenv = TestEnv::new();
+
+    testenv.add_os_entry("linux", "ss", "# ss\n\n> Linux ss.\n");
+    testenv.add_os_entry("openbsd", "pkg_add", "# pkg_add\n\n> OpenBSD pkg_add.\n");
+
+    testenv
+        .command()
+        .args(&["--os", "openbsd", "pkg_add"])
+        .assert()
+        .success()
+        .stdout(contains("OpenBSD pkg_add."));
+
+    // Fall back to Linux pages
+    testenv
+        .command()
+        .args(&["--os", "openbsd", "ss"])
+        .assert()
+        .success()
+        .stdout(contains("Linux ss."));
+
+    testenv
+        .command()
+        .args(&["--os", "netbsd", "pkg_add"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_no_common() {
+    let testenv = TestEnv::new();
+
+    testenv.add_entry("tar", "# tar\n\n> Common tar.\n");
+    testenv.add_os_entry("linux", "ss", "# ss\n\n> Linux ss.\n");
+
+    testenv
+        .command()
+        .args(&["--os", "linux", "--no-common", "ss"])
+        .assert()
+        .success()
+        .stdout(contains("Linux ss."));
+
+    testenv
+        .command()
+        .args(&["--os", "linux", "--no-common", "tar"])
+        .assert()
+        .failure();
+
+    testenv
+        .command()
+        .args(&["--os", "linux", "--no-common", "--list"])
+        .assert()
+        .success()
+        .stdout(contains("ss").and(contains("tar").not()));
+}
+
+#[test]
+/// Command names in Windows pages are highlighted case insensitively.
+fn test_windows_command_highlighting() {
+    let testenv = TestEnv::new();
+
+    let page =
+        "# get-childitem\n\n> List items.\n\n- List items:\n\n`Get-ChildItem -Path {{path}}`\n";
+    testenv.add_os_entry("windows", "get-childitem", page);
+    testenv.add_os_entry("linux", "get-childitem", page);
+    testenv.write_config("[style.command_name]\nbold = true\n");
+
+    testenv
+        .command()
+        .args(&["--color", "always", "--os", "windows", "get-childitem"])
+        .assert()
+        .success()
+        .stdout(contains("\x1b[1mGet-ChildItem"));
+
+    testenv
+        .command()
+        .args(&["--color", "always", "--os", "linux", "get-childitem"])
+        .assert()
+        .success()
+        .stdout(contains("\x1b[1mGet-ChildItem").not());
+}
+
+#[test]
+fn test_prefetch_list() {
+    let testenv = TestEnv::new();
+
+    testenv.add_entry("which", include_str!("which-markdown.expected"));
+    testenv.add_entry("git-checkout", "# git checkout\n\n> Checkout a branch.\n");
+    testenv.add_page_entry("pwd", "# pwd\n\n> Print working directory.\n");
+    testenv.write_config(format!(
+        "[directories]\ncustom_pages_dir = '{}'",
+        testenv.custom_pages_dir.path().to_str().unwrap()
+    ));
+
+    let list_path = testenv.input_dir.path().join("commands.txt");
+    let mut list = File::create(&list_path).unwrap();
+    list.write_all(b"which\n\ngit checkout\npwd\n").unwrap();
+
+    testenv
+        .command()
+        .args(&["--prefetch", list_path.to_str().unwrap()])
+        .assert()
+        .success()
+        .stderr(contains("All 3 pages are available."));
+
+    list.write_all(b"nonexistent\n").unwrap();
+
+    testenv
+        .command()
+        .args(&["--prefetch", list_path.to_str().unwrap()])
+        .assert()
+        .failure()
+        .stderr(
+            contains("Page nonexistent not found in cache")
+                .and(contains("1 of 4 pages are missing.")),
+        );
+
+    testenv
+        .command()
+        .args(&["--quiet", "--prefetch", list_path.to_str().unwrap()])
+        .assert()
+        .failure()
+        .stderr(contains("pages are missing").not());
+}
+
+#[test]
+fn test_manifest() {
+    let testenv = TestEnv::new();
+
+    testenv.add_entry("tar", "# tar\n\n> Archiving utility.\n");
+    testenv.add_entry("git-checkout", "# git checkout\n\n> Checkout a branch.\n");
+
+    let list_path = testenv.input_dir.path().join("commands.txt");
+    fs::write(&list_path, "tar\n\ngit checkout\n").unwrap();
+    testenv
+        .command()
+        .args(&["--manifest", list_path.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(
+            contains("Contents:\n\n  1. tar\n  2. git-checkout\n")
+                .and(contains("── tar ─────"))
+                .and(contains("─\n\n  Archiving utility."))
+                .and(contains("── git-checkout ───"))
+                .and(contains("Missing pages").not()),
+        );
+
+    let toml_path = testenv.input_dir.path().join("commands.toml");
+    fs::write(&toml_path, "commands = ['nonexistent', 'tar']\n").unwrap();
+    testenv
+        .command()
+        .args(&["--manifest", toml_path.to_str().unwrap()])
+        .assert()
+        .code(1)
+        .stdout(
+            contains("Contents:\n\n  1. tar\n\n")
+                .and(contains("Archiving utility."))
+                .and(contains("Missing pages:\n\n  nonexistent\n")),
+        );
+
+    testenv.write_config("[display]\npage_separator = '# {name}'\n");
+    testenv
+        .command()
+        .args(&["--manifest", list_path.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(contains("\n# tar\n\n  Archiving utility.").and(contains("\n# git-checkout\n")));
+}
+
+#[test]
+fn test_header_footer() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("tar", "# tar\n\n> Archiving utility.");
+    testenv.add_os_entry("linux", "ip", "# ip\n\n> Show interfaces.");
+    testenv.write_config(
+        "[display]\nheader = 'ACME docs'\nfooter = \"{name} ({platform})\\nSee the wiki\"\n",
+    );
+
+    testenv
+        .command()
+        .args(&["tar"])
+        .assert()
+        .success()
+        .stdout(similar(
+            "\n  ACME docs\n\n  Archiving utility.\n\n  tar (common)\n  See the wiki\n\n",
+        ));
+    testenv
+        .command()
+        .args(&["--os", "linux", "ip"])
+        .assert()
+        .success()
+        .stdout(contains("\n  ip (linux)\n"));
+
+    // Dimmed with styles
+    testenv
+        .command()
+        .args(&["--color", "always", "tar"])
+        .assert()
+        .success()
+        .stdout(contains("\n  \x1b[2mACME docs\x1b[0m\n"));
+
+    // Not part of the raw markdown
+    testenv
+        .command()
+        .args(&["--markdown", "tar"])
+        .assert()
+        .success()
+        .stdout(similar("# tar\n\n> Archiving utility.\n"));
+}
+
+#[test]
+fn test_hexdump() {
+    let testenv = TestEnv::new();
+
+    testenv.add_entry("tar", "# tar\r\n");
+
+    testenv
+        .command()
+        .args(&["--hexdump", "tar"])
+        .assert()
+        .success()
+        .stdout(similar(
+            "00000000  23 20 74 61 72 0d 0a                              |# tar..|\n00000007\n",
+        ));
+    testenv
+        .command()
+        .args(&["--hexdump", "nonexistent"])
+        .assert()
+        .code(1);
+}
+
+#[test]
+fn test_more_info_line() {
+    let testenv = TestEnv::new();
+
+    testenv.add_entry(
+        "tar",
+        "# tar\n\n> Archiving utility.\n> More information: <https://example.com/tar>.\n\n- Create an archive:\n\n`tar cf {{target.tar}}`\n",
+    );
+
+    testenv
+        .command()
+        .args(&["--color", "never", "tar"])
+        .assert()
+        .success()
+        .stdout(contains("  More information: <https://example.com/tar>.\n"));
+    testenv
+        .command()
+        .args(&["--format", "json", "tar"])
+        .assert()
+        .success()
+        .stdout(
+            contains(r#""description": "Archiving utility.","#)
+                .and(contains(r#""more_info_url": "https://example.com/tar","#)),
+        );
+
+    testenv.write_config("[display]\nhyperlink_more_info = true\n");
+    testenv
+        .command()
+        .args(&["--color", "always", "tar"])
+        .assert()
+        .success()
+        .stdout(contains(
+            "\x1b]8;;https://example.com/tar\x1b\\https://example.com/tar\x1b]8;;\x1b\\",
+        ));
+    testenv
+        .command()
+        .args(&["--color", "never", "tar"])
+        .assert()
+        .success()
+        .stdout(contains("\x1b]8").not());
+}
+
+#[test]
+fn test_oneline() {
+    let testenv = TestEnv::new();
+
+    testenv.add_entry("which", include_str!("which-markdown.expected"));
+    testenv.add_entry("empty", "# empty\n\n> No examples.\n");
+
+    testenv
+        .command()
+        .args(&["--color", "always", "--oneline", "which"])
+        .assert()
+        .success()
+        .stdout(similar("which executable"));
+
+    testenv
+        .command()
+        .args(&["--oneline", "empty"])
+        .assert()
+        .failure()
+        .stdout(is_empty());
+
+    testenv
+        .command()
+        .args(&["--oneline", "nonexistent"])
+        .assert()
+        .failure()
+        .stdout(is_empty())
+        .stderr(is_empty());
+}
+
+#[test]
+fn test_case_insensitive_lookup() {
+    let testenv = TestEnv::new();
+
+    testenv.add_entry("git", "# git\n\n> Lowercase git.\n");
+    testenv.add_entry("GIT", "# GIT\n\n> Uppercase git.\n");
+    testenv.add_os_entry("linux", "ss", "# ss\n\n> Linux ss.\n");
+
+    for name in &["git", "Git"] {
+        testenv
+            .command()
+            .args(&["--os", "linux", name])
+            .assert()
+            .success()
+            .stdout(contains("git."));
+    }
+
+    // Exact matches take precedence
+    testenv
+        .command()
+        .args(&["--os", "linux", "GIT"])
+        .assert()
+        .success()
+        .stdout(contains("Uppercase git."));
+    testenv
+        .command()
+        .args(&["--os", "linux", "git"])
+        .assert()
+        .success()
+        .stdout(contains("Lowercase git."));
+
+    testenv
+        .command()
+        .args(&["--os", "linux", "SS"])
+        .assert()
+        .success()
+        .stdout(contains("Linux ss."));
+}
+
+#[test]
+fn test_subcommand_lookup() {
+    let testenv = TestEnv::new();
+
+    testenv.add_entry("git", "# git\n\n> Top-level git.\n");
+    testenv.add_entry("git-commit", "# git commit\n\n> Subcommand git commit.\n");
+
+    testenv
+        .command()
+        .args(&["git", "commit"])
+        .assert()
+        .success()
+        .stdout(contains("Subcommand git commit."));
+
+    // Fall back to the top-level page
+    testenv
+        .command()
+        .args(&["git", "unknown-subcommand"])
+        .assert()
+        .success()
+        .stdout(contains("Top-level git."));
+
+    testenv
+        .command()
+        .args(&["nonexistent", "commit"])
+        .assert()
+        .failure()
+        .stderr(contains("Page nonexistent-commit not found in cache"));
+}
+
+#[test]
+fn test_rendering_links() {
+    let testenv = TestEnv::new();
+
+    testenv.add_entry(
+        "curl",
+        "# curl\n\n> Transfer data.\n> More information: <https://curl.se>.\n",
+    );
+    testenv.write_config("[style.link]\nbold = true\n");
+
+    testenv
+        .command()
+        .args(&["--color", "always", "curl"])
+        .assert()
+        .success()
+        .stdout(contains(
+            "More information: <\x1b[1mhttps://curl.se\x1b[0m>.",
+        ));
+}
+
+#[test]
+fn test_platform_list() {
+    let testenv = TestEnv::new();
+
+    testenv
+        .command()
+        .args(&["--platform-list"])
+        .assert()
+        .success()
+        .stdout(similar(
+            "linux\nosx\nsunos\nwindows\nfreebsd\nnetbsd\nopenbsd\n",
+        ));
+
+    testenv.add_entry("which", include_str!("which-markdown.expected"));
+    testenv.add_os_entry("linux", "ss", "# ss\n\n> Linux ss.\n");
+    testenv.add_os_entry("android", "am", "# am\n\n> Android am.\n");
+
+    testenv
+        .command()
+        .args(&["--platform-list"])
+        .assert()
+        .success()
+        .stdout(similar(
+            "linux (cached)\nosx\nsunos\nwindows\nfreebsd\nnetbsd\nopenbsd\n",
+        ));
+}
+
+#[test]
+fn test_language_list() {
+    let testenv = TestEnv::new();
+
+    testenv.add_entry("tar", "");
+    testenv.add_os_entry("linux", "ss", "");
+    let de_dir = testenv.cache_dir.path().join("tldr-master/pages.de/common");
+    create_dir_all(&de_dir).unwrap();
+    fs::write(de_dir.join("tar.md"), "").unwrap();
+    create_dir_all(testenv.cache_dir.path().join("tldr-master/scripts")).unwrap();
+
+    testenv
+        .command()
+        .args(&["--language-list"])
+        .assert()
+        .success()
+        .stdout(similar("de\nen\n"));
+    testenv
+        .command()
+        .args(&["--language-list", "--count"])
+        .assert()
+        .success()
+        .stdout(similar("de: 1\nen: 2\n"));
+}
+
+#[test]
+fn test_emphasize_first_example() {
+    let testenv = TestEnv::new();
+
+    testenv.add_entry("which", include_str!("which-markdown.expected"));
+    testenv.write_config(
+        "[display]\nemphasize_first_example = true\n[style.example_text]\nforeground = 'green'\n",
+    );
+
+    testenv
+        .command()
+        .args(&["--color", "always", "which"])
+        .assert()
+        .success()
+        .stdout(
+            contains("\x1b[1;32mSearch the PATH")
+                .and(contains("\x1b[32mIf there are multiple executables")),
+        );
+
+    testenv
+        .command()
+        .args(&["--color", "never", "which"])
+        .assert()
+        .success()
+        .stdout(contains("\x1b[").not());
+}
+
+#[test]
+fn test_wrap_code() {
+    let testenv = TestEnv::new();
+
+    testenv.add_entry(
+        "tar",
+        "# tar\n\n> Archiving utility.\n\n- Create an archive:\n\n`tar cf {{path/to/target.tar}} {{file1 file2}}`\n",
+    );
+
+    // Not wrapped by default
+    testenv
+        .command()
+        .args(&["--color", "never", "tar"])
+        .env("COLUMNS", "32")
+        .assert()
+        .success()
+        .stdout(contains("      tar cf path/to/target.tar file1 file2\n"));
+
+    testenv.write_config("[display]\nwrap_code = true\n");
+
+    for flag in &["--no-styling", "--color=never"] {
+        testenv
+            .command()
+            .args(&[flag, "tar"])
+            .env("COLUMNS", "32")
+            .assert()
+            .success()
+            .stdout(contains(
+                "      tar cf path/to/target.tar\n          file1 file2\n",
+            ));
+    }
+}
+
+#[test]
+fn test_max_width() {
+    let testenv = TestEnv::new();
+
+    testenv.add_entry(
+        "tar",
+        "# tar\n\n> Archiving utility.\n\n- Create an archive:\n\n`tar cf {{path/to/target.tar}} {{file1 file2}}`\n",
+    );
+    testenv.write_config("[display]\nwrap_code = true\nmax_width = 32\n");
+
+    // Wide terminals are capped
+    testenv
+        .command()
+        .args(&["--color", "never", "tar"])
+        .env("COLUMNS", "200")
+        .assert()
+        .success()
+        .stdout(contains(
+            "      tar cf path/to/target.tar\n          file1 file2\n",
+        ));
+
+    // Narrower terminals are still used in full
+    testenv
+        .command()
+        .args(&["--color", "never", "tar"])
+        .env("COLUMNS", "28")
+        .assert()
+        .success()
+        .stdout(contains("      tar cf\n          path/to/target.tar\n"));
+
+    // 0 is the terminal width
+    testenv.write_config("[display]\nwrap_code = true\nmax_width = 0\n");
+    testenv
+        .command()
+        .args(&["--color", "never", "tar"])
+        .env("COLUMNS", "200")
+        .assert()
+        .success()
+        .stdout(contains("      tar cf path/to/target.tar file1 file2\n"));
+}
+
+#[test]
+fn test_markdown_rendering() {
+    let testenv = TestEnv::new();
+
+    testenv.add_entry("which", include_str!("which-markdown.expected"));
+
+    let expected = include_str!("which-markdown.expected");
+    testenv
+        .command()
+        .args(&["-m", "which"])
+        .assert()
+        .success()
+        .stdout(similar(expected));
+}
+
+fn _test_correct_rendering(
+    input_file: &str,
+    filename: &str,
+    expected: &'static str,
+    color_option: &str,
+) {
+    let testenv = TestEnv::new();
+
+    // Create input file
+    let file_path = testenv.input_dir.path().join(filename);
+    println!("Testfile path: {:?}", &file_path);
+    let mut file = File::create(&file_path).unwrap();
+    file.write_all(input_file.as_bytes()).unwrap();
+
+    // Captured output with forced color ends with a reset
+    let expected = if color_option == "always" {
+        format!("{}\x1b[0m", expected)
+    } else {
+        expected.to_string()
+    };
+    testenv
+        .command()
+        .args(&["--color", color_option, "-f", &file_path.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(similar(expected));
+}
+
+/// Wrapping of a page with non-Latin text, counting letters with combining
+/// marks as a single column.
+#[test]
+fn test_examples_only() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "tar",
+        "# tar\n\n> Archiving utility.\n> Often combined with gzip.\n\
+         > More information: <https://example.com/tar>.\n\n\
+         - Extract an archive:\n\n`tar xf {{source.tar}}`\n",
+    );
+
+    let expected = "\n  Extract an archive:\n\n      tar xf source.tar\n\n";
+    for flag in &["--no-styling", "--color=never"] {
+        testenv
+            .command()
+            .args(&[flag, "--examples-only", "tar"])
+            .assert()
+            .success()
+            .stdout(similar(expected));
+    }
+
+    // The raw markdown is not changed
+    testenv
+        .command()
+        .args(&["--markdown", "--examples-only", "tar"])
+        .assert()
+        .success()
+        .stdout(contains("> Archiving utility."));
+
+    // Neither is the JSON output
+    testenv
+        .command()
+        .args(&["--format", "json", "--examples-only", "tar"])
+        .assert()
+        .success()
+        .stdout(contains(
+            r#""description": "Archiving utility.\nOften combined with gzip.""#,
+        ))
+        .stdout(contains(r#""more_info_url": "https://example.com/tar""#));
+}
+
+#[test]
+fn test_placeholder_values() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "tar",
+        "# tar\n\n> Archiving utility.\n\n- Create an archive:\n\n`tar cf {{file}} {{dir}}`\n\n\
+         - Extract an archive:\n\n`tar xf {{file}} -C {{path/to/dir}}`\n",
+    );
+
+    for flag in &["--no-styling", "--color=never"] {
+        testenv
+            .command()
+            .args(&[
+                flag,
+                "--set",
+                "file=backup.tar",
+                "--set",
+                "dir=./data",
+                "tar",
+            ])
+            .assert()
+            .success()
+            .stdout(contains("      tar cf backup.tar ./data\n"))
+            .stdout(contains("      tar xf backup.tar -C path/to/dir\n"));
+    }
+
+    // Resolved values are not styled as placeholders
+    testenv
+        .command()
+        .args(&["--color", "always", "--set", "file=backup.tar", "tar"])
+        .assert()
+        .success()
+        .stdout(contains("\x1b[36mtar cf backup.tar \x1b[4mdir\x1b[0m"));
+
+    testenv
+        .command()
+        .args(&["--set", "file", "tar"])
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(contains(
+            "Invalid placeholder value: file (expected KEY=VALUE)",
+        ));
+}
+
+#[test]
+fn test_expand_env() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "deploy",
+        "# deploy\n\n> Deploy.\n\n- Deploy to the host:\n\n`deploy $TLDR_HOST ${TLDR_UNDEFINED} {{dir}}`\n",
+    );
+
+    // Off by default
+    testenv
+        .command()
+        .env("TLDR_HOST", "example.com")
+        .args(&["--color", "never", "deploy"])
+        .assert()
+        .success()
+        .stdout(contains("deploy $TLDR_HOST ${TLDR_UNDEFINED} dir\n"));
+
+    testenv.write_config("[display]\nexpand_env = true\n");
+    testenv
+        .command()
+        .env("TLDR_HOST", "example.com")
+        .env_remove("TLDR_UNDEFINED")
+        .args(&["--color", "never", "deploy"])
+        .assert()
+        .success()
+        .stdout(contains("deploy example.com ${TLDR_UNDEFINED} dir\n"));
+}
+
+#[test]
+fn test_as_script() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "tar",
+        "# tar\n\n> Archiving utility.\n\n- Create an archive:\n\n`tar cf {{file}} {{dir}}`\n\n\
+         - Extract an archive:\n\n`tar xf {{file}}`\n",
+    );
+
+    testenv
+        .command()
+        .args(&["--as-script", "--set", "file=backup.tar", "tar"])
+        .assert()
+        .success()
+        .stdout(similar(
+            "#!/bin/sh\n\n# tar\n# Archiving utility.\n\n\
+             # Create an archive:\n# TODO: replace {{dir}}\n# tar cf backup.tar {{dir}}\n\n\
+             # Extract an archive:\ntar xf backup.tar\n",
+        ));
+}
+
+#[test]
+fn test_positional_placeholders() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "cp",
+        "# cp\n\n> Copy files.\n\n- Copy a file:\n\n`cp {{file}} {{dir}}/{{file}}`\n\n\
+         - Copy a directory:\n\n`cp -r {{source}} {{target}}`\n",
+    );
+
+    testenv
+        .command()
+        .args(&["--color", "never", "--placeholders", "positional", "cp"])
+        .assert()
+        .success()
+        .stdout(contains("      cp $1 $2/$1\n"))
+        .stdout(contains("      cp -r $1 $2\n"));
+
+    // The placeholders left by `--set` are numbered
+    testenv
+        .command()
+        .args(&[
+            "--as-script",
+            "--placeholders",
+            "positional",
+            "--set",
+            "source=src",
+            "cp",
+        ])
+        .assert()
+        .success()
+        .stdout(similar(
+            "#!/bin/sh\n\n# cp\n# Copy files.\n\n\
+             # Copy a file:\ncp $1 $2/$1\n\n\
+             # Copy a directory:\ncp -r src $1\n",
+        ));
+
+    // Named placeholders by default
+    testenv
+        .command()
+        .args(&["--color", "never", "cp"])
+        .assert()
+        .success()
+        .stdout(contains("      cp file dir/file\n"));
+}
+
+#[test]
+fn test_inline_layout() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "tar",
+        "# tar\n\n> Archiving utility.\n\n- Extract:\n\n`tar xf {{source.tar}}`\n\n\
+         - Create an archive:\n\n`tar cf {{target.tar}} {{file}}`\n",
+    );
+    testenv.write_config("[display]\nlayout = \"inline\"\n");
+
+    let inline = "\n  Archiving utility.\n\n  Extract:            tar xf source.tar\n\n  \
+                  Create an archive:  tar cf target.tar file\n\n";
+    for flag in &["--no-styling", "--color=never"] {
+        testenv
+            .command()
+            .args(&[flag, "tar"])
+            .env("COLUMNS", "44")
+            .assert()
+            .success()
+            .stdout(similar(inline));
+
+        // Fall back to the stacked layout if the examples don't fit
+        testenv
+            .command()
+            .args(&[flag, "tar"])
+            .env("COLUMNS", "43")
+            .assert()
+            .success()
+            .stdout(contains("  Extract:\n\n      tar xf source.tar\n"));
+    }
+}
+
+#[test]
+fn test_wrap_code_non_latin() {
+    let testenv = TestEnv::new();
+    testenv.write_config("[display]\nwrap_code = true\n");
+
+    let file_path = testenv.input_dir.path().join("tar-ar.md");
+    let mut file = File::create(&file_path).unwrap();
+    file.write_all(include_str!("tar-ar.md").as_bytes())
+        .unwrap();
+
+    testenv
+        .command()
+        .args(&["--color", "never", "-f", &file_path.to_str().unwrap()])
+        .env("COLUMNS", "34")
+        .assert()
+        .success()
+        .stdout(similar(include_str!("tar-ar-wrapped.expected")));
+}
+
+/// An end-to-end integration test for direct file rendering (v1 syntax).
+#[test]
+fn test_correct_rendering_v1() {
+    _test_correct_rendering(
+        include_str!("inkscape-v1.md"),
+        "inkscape-v1.md",
+        include_str!("inkscape-default.expected"),
+        "always",
+    );
+}
+
+/// An end-to-end integration test for direct file rendering (v2 syntax).
+#[test]
+fn test_correct_rendering_v2() {
+    _test_correct_rendering(
+        include_str!("inkscape-v2.md"),
+        "inkscape-v2.md",
+        include_str!("inkscape-default.expected"),
+        "always",
+    );
+}
+
+#[test]
+/// An end-to-end integration test for direct file rendering with the `--color auto` option. This
+/// will not use styling since output is not stdout.
+fn test_rendering_color_auto() {
+    _test_correct_rendering(
+        include_str!("inkscape-v2.md"),
+        "inkscape-v2.md",
+        include_str!("inkscape-default-no-color.expected"),
+        "auto",
+    );
+}
+
+#[test]
+/// An end-to-end integration test for direct file rendering with the `--color never` option.
+fn test_rendering_color_never() {
+    _test_correct_rendering(
+        include_str!("inkscape-v2.md"),
+        "inkscape-v2.md",
+        include_str!("inkscape-default-no-color.expected"),
+        "never",
+    );
+}
+
+#[test]
+/// An end-to-end integration test for direct file rendering with the `--no-styling` option. This
+/// must match the `--color never` output, even if color is forced.
+fn test_rendering_no_styling() {
+    let testenv = TestEnv::new();
+
+    let file_path = testenv.input_dir.path().join("inkscape-v2.md");
+    let mut file = File::create(&file_path).unwrap();
+    file.write_all(include_str!("inkscape-v2.md").as_bytes())
+        .unwrap();
+
+    testenv
+        .command()
+        .args(&[
+            "--no-styling",
+            "--color",
+            "always",
+            "-f",
+            &file_path.to_str().unwrap(),
+        ])
+        .assert()
+        .success()
+        .stdout(similar(include_str!("inkscape-default-no-color.expected")));
+}
+
+#[test]
+/// An end-to-end integration test for rendering to a file with `--output`. Styling is disabled
+/// by default, since the output is not a terminal.
+fn test_rendering_to_output_file() {
+    let testenv = TestEnv::new();
+
+    let file_path = testenv.input_dir.path().join("inkscape-v2.md");
+    let mut file = File::create(&file_path).unwrap();
+    file.write_all(include_str!("inkscape-v2.md").as_bytes())
+        .unwrap();
+
+    // Existing files are overwritten
+    let output_path = testenv.input_dir.path().join("output.txt");
+    File::create(&output_path)
+        .unwrap()
+        .write_all(b"old content")
+        .unwrap();
+
+    testenv
+        .command()
+        .args(&[
+            "--output",
+            output_path.to_str().unwrap(),
+            "-f",
+            file_path.to_str().unwrap(),
+        ])
+        .assert()
+        .success()
+        .stdout(is_empty());
+
+    assert_eq!(
+        std::fs::read_to_string(&output_path).unwrap(),
+        include_str!("inkscape-default-no-color.expected")
+    );
+
+    testenv
+        .command()
+        .args(&[
+            "--output",
+            testenv.input_dir.path().to_str().unwrap(),
+            "-f",
+            file_path.to_str().unwrap(),
+        ])
+        .assert()
+        .failure()
+        .stderr(contains("is a directory"));
+}
+
+/// With `TEALDEER_FORCE_COLOR=1`, the output is styled with the default palette, regardless of
+/// the environment and the style config.
+#[test]
+fn test_force_color() {
+    let testenv = TestEnv::new();
+
+    testenv.write_config(include_str!("config.toml"));
+    testenv.add_entry("inkscape-v2", include_str!("inkscape-v2.md"));
+
+    testenv
+        .command()
+        .args(&["--theme", "ocean", "inkscape-v2"])
+        .env("TEALDEER_FORCE_COLOR", "1")
+        .env("NO_COLOR", "1")
+        .assert()
+        .success()
+        .stdout(similar(include_str!("inkscape-default.expected")));
+
+    testenv
+        .command()
+        .args(&["--color", "never", "inkscape-v2"])
+        .env("TEALDEER_FORCE_COLOR", "1")
+        .assert()
+        .success()
+        .stdout(similar(include_str!("inkscape-default-no-color.expected")));
+}
+
+/// With `--color always`, output that is captured (not written to a terminal) ends with a reset
+/// escape sequence and has LF line endings, even for pages with CRLF line endings.
+#[test]
+fn test_forced_color_captured() {
+    let testenv = TestEnv::new();
+    testenv.write_config("[style.command_name]\nforeground = \"cyan\"\n");
+    testenv.add_entry(
+        "tar",
+        "# tar\r\n\r\n> Archiving utility.\r\n\r\n- Extract an archive:\r\n\r\n`tar xf {{source.tar}}`\r\n",
+    );
+
+    let output_path = testenv.input_dir.path().join("output.txt");
+    testenv
+        .command()
+        .args(&[
+            "--color",
+            "always",
+            "--output",
+            output_path.to_str().unwrap(),
+            "tar",
+        ])
+        .assert()
+        .success();
+    let output = std::fs::read_to_string(&output_path).unwrap();
+    assert!(output.contains("\x1b[36mtar"), "{:?}", output);
+    assert!(output.ends_with("\n\x1b[0m"), "{:?}", output);
+    assert!(!output.contains('\r'), "{:?}", output);
+
+    testenv
+        .command()
+        .args(&["--color", "always", "tar"])
+        .assert()
+        .success()
+        .stdout(ends_with("\n\x1b[0m"));
+
+    // Without forced color, nothing is appended
+    testenv
+        .command()
+        .args(&["tar"])
+        .assert()
+        .success()
+        .stdout(ends_with("\n\n"));
+}
+
+#[test]
+fn test_interactive_unavailable() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("which", include_str!("which-markdown.expected"));
+
+    // Not built with the `interactive` feature
+    testenv
+        .command()
+        .args(&["--interactive"])
+        .assert()
+        .code(2)
+        .stderr(contains("The interactive page picker is not available"));
+}
+
+#[test]
+fn test_benchmark_unavailable() {
+    let testenv = TestEnv::new();
+
+    // Not built with the `benchmark` feature
+    testenv
+        .command()
+        .args(&["--benchmark", "10"])
+        .assert()
+        .code(2)
+        .stderr(contains("The benchmark is not available"));
+}
+
+#[test]
+fn test_watch_unavailable() {
+    let testenv = TestEnv::new();
+
+    let file_path = testenv.input_dir.path().join("inkscape-v2.md");
+    fs::write(&file_path, include_str!("inkscape-v2.md")).unwrap();
+
+    // Not built with the `watch` feature
+    testenv
+        .command()
+        .args(&["--render", file_path.to_str().unwrap(), "--watch"])
+        .assert()
+        .code(2)
+        .stderr(contains("The live preview is not available"));
+    testenv
+        .command()
+        .args(&["--watch"])
+        .assert()
+        .code(2)
+        .stderr(contains("--watch can only be used with --render"));
+}
+
+#[test]
+fn test_theme_preview() {
+    let testenv = TestEnv::new();
+
+    // No cache is needed
+    testenv
+        .command()
+        .args(&["--theme-preview", "--color", "never"])
+        .assert()
+        .success()
+        .stdout(contains(
+            "  A synthetic page to preview the styles of tealdeer.",
+        ))
+        .stdout(contains("      preview --input path/to/file"));
+
+    testenv
+        .command()
+        .args(&["--theme-preview"])
+        .env("TEALDEER_FORCE_COLOR", "1")
+        .assert()
+        .success()
+        .stdout(contains("\x1b[4;34mhttps://tldr.sh\x1b[0m"))
+        .stdout(contains("\x1b[32mRun the command:\x1b[0m"));
+}
+
+/// An end-to-end integration test for rendering with custom syntax config.
+#[test]
+fn test_correct_rendering_with_config() {
+    let testenv = TestEnv::new();
+
+    // Setup config file
+    // TODO should be config::CONFIG_FILE_NAME
+    let config_file_path = testenv.config_dir.path().join("config.toml");
+    println!("Config path: {:?}", &config_file_path);
+
+    let mut config_file = File::create(&config_file_path).unwrap();
+    config_file
+        .write_all(include_str!("config.toml").as_bytes())
+        .unwrap();
+
+    // Create input file
+    let file_path = testenv.input_dir.path().join("inkscape-v2.md");
+    println!("Testfile path: {:?}", &file_path);
+
+    let mut file = File::create(&file_path).unwrap();
+    file.write_all(include_str!("inkscape-v2.md").as_bytes())
+        .unwrap();
+
+    // Load expected output
+    let expected = format!("{}\x1b[0m", include_str!("inkscape-with-config.expected"));
+
+    testenv
+        .command()
+        .args(&["--color", "always", "-f", &file_path.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(similar(expected));
+}
+
+/// An end-to-end integration test for rendering with a command prefix.
+#[test]
+fn test_rendering_with_command_prefix() {
+    let testenv = TestEnv::new();
+
+    testenv.write_config("[display]\ncommand_prefix = '$ '\n");
+    testenv.add_entry("which", include_str!("which-markdown.expected"));
+
+    for flag in &["--no-styling", "--color=never"] {
+        testenv
+            .command()
+            .args(&[flag, "which"])
+            .assert()
+            .success()
+            .stdout(contains("\n      $ which executable\n"));
+    }
+
+    testenv
+        .command()
+        .args(&["-m", "which"])
+        .assert()
+        .success()
+        .stdout(similar(include_str!("which-markdown.expected")));
+}
+
+#[test]
+/// The `--theme` option selects a built-in style preset, explicit style entries in the config
+/// file take precedence.
+fn test_rendering_with_theme() {
+    let testenv = TestEnv::new();
+
+    testenv.add_entry("which", include_str!("which-markdown.expected"));
+
+    // The monochrome theme uses attributes only
+    let assert = testenv
+        .command()
+        .args(&["--color", "always", "--theme", "monochrome", "which"])
+        .assert()
+        .success()
+        .stdout(contains("\x1b[1m"));
+    let stdout = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+    assert!(
+        !stdout.contains("\x1b[3"),
+        "unexpected color in {:?}",
+        stdout
+    );
+
+    testenv.write_config("[display]\ntheme = 'ocean'\n[style.example_code]\nforeground = 'red'\n");
+
+    // The command line option overrides the theme from the config file
+    let assert = testenv
+        .command()
+        .args(&["--color", "always", "--theme", "monochrome", "which"])
+        .assert()
+        .success()
+        .stdout(contains("\x1b[31m"));
+    let stdout = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+    assert!(
+        !stdout.contains("\x1b[34m"),
+        "unexpected color in {:?}",
+        stdout
+    );
+
+    testenv
+        .command()
+        .args(&["--color", "always", "which"])
+        .assert()
+        .success()
+        .stdout(contains("\x1b[1;34m").and(contains("\x1b[31m")));
+
+    testenv
+        .command()
+        .args(&["--theme", "solarized", "which"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_spaces_find_command() {
+    let testenv = TestEnv::new();
+
+    testenv
+        .command()
+        .args(&["--update"])
+        .assert()
+        .success()
+        .stderr(contains("Successfully updated cache."));
+
+    testenv
+        .command()
+        .args(&["git", "checkout"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_pager_flag_enable() {
+    let testenv = TestEnv::new();
+
+    testenv
+        .command()
+        .args(&["--update"])
+        .assert()
+        .success()
+        .stderr(contains("Successfully updated cache."));
+
+    testenv
+        .command()
+        .args(&["--pager", "which"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_list_flag_rendering() {
+    let testenv = TestEnv::new();
+
+    testenv
+        .command()
+        .args(&["--list"])
+        .assert()
+        .failure()
+        .stderr(contains("Cache not found. Please run `tldr --update`."));
+
+    testenv.add_entry("foo", "");
+
+    testenv
+        .command()
+        .args(&["--list"])
+        .assert()
+        .success()
+        .stdout("foo\n");
+
+    testenv.add_entry("bar", "");
+    testenv.add_entry("baz", "");
+    testenv.add_entry("qux", "");
+
+    testenv
+        .command()
+        .args(&["--list"])
+        .assert()
+        .success()
+        .stdout("bar\nbaz\nfoo\nqux\n");
+
+    testenv
+        .command()
+        .args(&["--list", "--limit", "2"])
+        .assert()
+        .success()
+        .stdout("bar\nbaz\n");
+
+    // Platform specific pages are merged with the common pages
+    testenv.add_os_entry("linux", "bar", "");
+    testenv.add_os_entry("linux", "ss", "");
+
+    testenv
+        .command()
+        .args(&["--os", "linux", "--list", "--offset", "2", "--limit", "2"])
+        .assert()
+        .success()
+        .stdout("foo\nqux\n");
+
+    testenv
+        .command()
+        .args(&["--os", "linux", "--list", "--offset", "3"])
+        .assert()
+        .success()
+        .stdout("qux\nss\n");
+}
+
+#[test]
+fn test_indentation() {
+    let testenv = TestEnv::new();
+
+    testenv.add_entry(
+        "tar",
+        "# tar\n\n> Archiving utility.\n\n- Create an archive:\n\n`tar cf {{target.tar}} {{file1 file2}}`\n",
+    );
+    testenv.write_config("[display]\ncode_indent = 4\ndescription_indent = 0\nwrap_code = true\n");
+
+    testenv
+        .command()
+        .args(&["--color", "never", "tar"])
+        .env("COLUMNS", "24")
+        .assert()
+        .success()
+        .stdout("\nArchiving utility.\n\nCreate an archive:\n\n    tar cf target.tar\n        file1 file2\n\n");
+}
+
+#[test]
+fn test_export() {
+    let testenv = TestEnv::new();
+    let export_path = testenv.input_dir.path().join("pages.tar.gz");
+    let export_arg = export_path.to_str().unwrap();
+
+    testenv
+        .command()
+        .args(&["--export", export_arg])
+        .assert()
+        .code(3)
+        .stderr(contains("Cache not found"));
+
+    testenv.add_entry("which", include_str!("which-markdown.expected"));
+
+    testenv
+        .command()
+        .args(&["--export", export_arg])
+        .assert()
+        .success()
+        .stderr(contains("Exported cache to"));
+    // gzip magic bytes
+    assert!(fs::read(&export_path).unwrap().starts_with(&[0x1f, 0x8b]));
+}
+
+#[test]
+fn test_list_custom_pages() {
+    let testenv = TestEnv::new();
+
+    testenv.add_entry("tar", "# tar\n\n> Archiving utility.\n");
+    testenv.add_entry("pwd", "# pwd\n\n> Print working directory.\n");
+    testenv.add_page_entry("pwd", "# pwd\n\n> Custom pwd.\n");
+    testenv.add_page_entry("foo", "# foo\n\n> Custom foo.\n");
+    testenv.add_patch_entry("tar", "# tar\n\n- Custom example:\n\n`tar`\n");
+    testenv.write_config(format!(
+        "[directories]\ncustom_pages_dir = '{}'",
+        testenv.custom_pages_dir.path().to_str().unwrap()
+    ));
+
+    // Without styling, custom pages are not marked
+    testenv
+        .command()
+        .args(&["--list"])
+        .assert()
+        .success()
+        .stdout("foo\npwd\ntar\n");
+
+    testenv
+        .command()
+        .args(&["--list", "--color", "always"])
+        .assert()
+        .success()
+        .stdout("foo [custom]\npwd [custom]\ntar\n");
+
+    testenv
+        .command()
+        .args(&["--list", "--custom-only"])
+        .assert()
+        .success()
+        .stdout("foo\npwd\n");
+
+    testenv
+        .command()
+        .args(&["--list", "--long", "--color", "always"])
+        .env("COLUMNS", "80")
+        .assert()
+        .success()
+        .stdout(
+            "foo [custom]  Custom foo.\n\
+             pwd [custom]  Custom pwd.\n\
+             tar           Archiving utility.\n",
+        );
+}
+
+#[test]
+fn test_invalid_user_agent() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("which", include_str!("which-markdown.expected"));
+    testenv.write_config("[updates]\nuser_agent = \"tealdeer\\nX-Injected: 1\"\n");
+
+    testenv
+        .command()
+        .args(&["which"])
+        .assert()
+        .code(4)
+        .stderr(contains("Invalid user_agent"));
+}
+
+#[test]
+fn test_invalid_header() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("which", include_str!("which-markdown.expected"));
+    testenv.write_config("[updates.headers]\n\"X Mirror\" = \"eu\"\n");
+
+    testenv
+        .command()
+        .args(&["which"])
+        .assert()
+        .code(4)
+        .stderr(contains("Invalid header name \"X Mirror\""));
+
+    testenv.write_config("[updates.headers]\nX-Mirror = \"eu\\r\\nX-Injected: 1\"\n");
+    testenv
+        .command()
+        .args(&["which"])
+        .assert()
+        .code(4)
+        .stderr(contains("Invalid value of header \"X-Mirror\""));
+}
+
+#[test]
+fn test_invalid_delta_url() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("which", include_str!("which-markdown.expected"));
+    testenv.write_config("[updates]\ndelta_url = \"https://tldr.example.com/pages.delta\"\n");
+
+    testenv
+        .command()
+        .args(&["which"])
+        .assert()
+        .code(4)
+        .stderr(contains("Invalid delta_url").and(contains("it must contain {etag}")));
+}
+
+#[test]
+fn test_invalid_archive_url() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("which", include_str!("which-markdown.expected"));
+    testenv.write_config("[updates]\narchive_url = \"/srv/tldr/pages.zip\"\n");
+
+    testenv
+        .command()
+        .args(&["which"])
+        .assert()
+        .code(4)
+        .stderr(contains("Invalid archive_url").and(contains("only http:// and https://")));
+}
+
+#[test]
+fn test_grep() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "tar",
+        "# tar\n\n> Archiving utility.\n\n- Extract an archive:\n\n`tar xf {{source.tar}}`\n\n\
+         - Create an archive:\n\n`tar cf {{target.tar}} {{file}}`\n\n\
+         - List the contents of an archive:\n\n`tar tf {{source.tar}}`\n",
+    );
+
+    testenv
+        .command()
+        .args(&["--grep", "CREATE", "tar"])
+        .assert()
+        .success()
+        .stdout(similar(
+            "\n  Archiving utility.\n\n  Create an archive:\n\n      tar cf target.tar file\n\n  \
+             2 examples not matching \"CREATE\" were hidden.\n\n",
+        ));
+
+    // Nothing is hidden from the JSON output
+    testenv
+        .command()
+        .args(&["--grep", "create", "--format", "json", "tar"])
+        .assert()
+        .success()
+        .stdout(contains("tar xf {{source.tar}}"))
+        .stdout(contains("tar tf {{source.tar}}"));
+
+    // Filtering out all examples is an error
+    testenv
+        .command()
+        .args(&["--grep", "compress", "tar"])
+        .assert()
+        .code(1)
+        .stdout(is_empty())
+        .stderr(similar("No examples matched \"compress\"\n"));
+    testenv
+        .command()
+        .args(&["--grep", "compress", "--quiet", "tar"])
+        .assert()
+        .code(1)
+        .stderr(is_empty());
+    testenv.write_config("[display]\nno_match_message = \"Nothing about {pattern}\"\n");
+    testenv
+        .command()
+        .args(&["--grep", "compress", "tar"])
+        .assert()
+        .code(1)
+        .stderr(similar("Nothing about compress\n"));
+}
+
+#[test]
+fn test_dedup() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "tar",
+        "# tar\n\n> Archiving utility.\n\n- Extract an archive:\n\n`tar xf {{source.tar}}`\n\n\
+         - Create an archive:\n\n`tar cf {{target.tar}} {{file}}`\n\n\
+         - Extract a compressed archive:\n\n`tar xf {{source.tar.gz}}`\n",
+    );
+
+    testenv
+        .command()
+        .args(&["--dedup", "tar"])
+        .assert()
+        .success()
+        .stdout(similar(
+            "\n  Archiving utility.\n\n  Extract an archive:\n\n      tar xf source.tar\n\n  \
+             Create an archive:\n\n      tar cf target.tar file\n\n  \
+             1 duplicate example was collapsed.\n\n",
+        ));
+
+    // Nothing is collapsed in the JSON output
+    testenv
+        .command()
+        .args(&["--dedup", "--format", "json", "tar"])
+        .assert()
+        .success()
+        .stdout(contains("tar xf {{source.tar.gz}}"));
+}
+
+#[test]
+fn test_networking_disabled() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("which", include_str!("which-markdown.expected"));
+    testenv.write_config(
+        "[updates]\nnetworking = false\nauto_update = true\nauto_update_interval_hours = 0\n",
+    );
+
+    testenv
+        .command()
+        .args(&["--update"])
+        .assert()
+        .code(5)
+        .stderr(contains("Networking is disabled in the config"));
+    testenv
+        .command()
+        .args(&["--check-update"])
+        .assert()
+        .code(5)
+        .stderr(contains(
+            "Could not check for a newer release: Networking is disabled",
+        ));
+    testenv
+        .command()
+        .args(&["--quiet", "--check-update"])
+        .assert()
+        .code(5)
+        .stderr(is_empty());
+
+    // No automatic update is attempted
+    testenv
+        .command()
+        .args(&["which"])
+        .assert()
+        .success()
+        .stderr(contains("Networking").not());
+}
+
+#[test]
+fn test_cache_readonly() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("which", include_str!("which-markdown.expected"));
+    testenv.write_config(
+        "[directories]\ncache_readonly = true\n\
+         [updates]\nauto_update = true\nauto_update_interval_hours = 0\n",
+    );
+
+    testenv
+        .command()
+        .args(&["--update"])
+        .assert()
+        .code(5)
+        .stderr(contains("updates are disabled by policy"));
+    testenv
+        .command()
+        .args(&["--clear-cache"])
         .assert()
         .failure()
-        .stderr(contains("Cache not found. Please run `tldr --update`."));
+        .stderr(contains("clearing it is disabled by policy"));
+    assert!(testenv.cache_dir.path().join("tldr-master").is_dir());
 
-    testenv.add_entry("foo", "");
+    // Pages are still rendered, without an automatic update
+    testenv
+        .command()
+        .args(&["which"])
+        .assert()
+        .success()
+        .stdout(contains("Locate a program"))
+        .stderr(contains("read-only").not());
+
+    // The format version marker isn't written into a read-only cache
+    assert!(!testenv.cache_dir.path().join("format-version").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_readonly_cache_without_format_version() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let testenv = TestEnv::new();
+    testenv.add_entry("tar", "# tar\n\n> Archiving utility.");
+    let cache_dir = testenv.cache_dir.path();
+    assert!(!cache_dir.join("format-version").exists());
+
+    // A cache from an older version without the marker is still rendered
+    fs::set_permissions(cache_dir, fs::Permissions::from_mode(0o555)).unwrap();
+    let assert = testenv.command().args(&["tar"]).assert();
+    fs::set_permissions(cache_dir, fs::Permissions::from_mode(0o755)).unwrap();
+    assert
+        .success()
+        .stdout(contains("Archiving utility."))
+        .stderr(contains("Could not").not());
+}
+
+#[test]
+fn test_list_count() {
+    let testenv = TestEnv::new();
+
+    testenv.add_entry("tar", "");
+    testenv.add_entry("ip", "");
+    testenv.add_os_entry("linux", "ip", "");
+    testenv.add_os_entry("linux", "apt", "");
+    testenv.add_os_entry("osx", "brew", "");
+
+    testenv
+        .command()
+        .args(&["--os", "linux", "--list", "--count"])
+        .assert()
+        .success()
+        .stdout(similar("3\n"));
+    testenv
+        .command()
+        .args(&["--list", "--count", "--all-platforms"])
+        .assert()
+        .success()
+        .stdout(similar("common: 2\nlinux: 2\nosx: 1\ntotal: 4\n"));
+}
+
+#[test]
+fn test_list_null() {
+    let testenv = TestEnv::new();
+
+    testenv.add_entry("tar", "");
+    testenv.add_entry("ip", "");
+
+    testenv
+        .command()
+        .args(&["--list", "-0"])
+        .assert()
+        .success()
+        .stdout(similar("ip\0tar\0"));
+    testenv
+        .command()
+        .args(&["--list", "--null", "--limit", "1"])
+        .assert()
+        .success()
+        .stdout(similar("ip\0"));
+}
+
+#[test]
+fn test_list_group_by_platform() {
+    let testenv = TestEnv::new();
+
+    testenv.add_entry("tar", "");
+    testenv.add_entry("ip", "");
+    testenv.add_os_entry("linux", "ip", "");
+    testenv.add_os_entry("linux", "apt", "");
+    testenv.add_os_entry("osx", "brew", "");
+    testenv.add_page_entry("mytool", "");
+    testenv.write_config(format!(
+        "[directories]\ncustom_pages_dir = '{}'",
+        testenv.custom_pages_dir.path().to_str().unwrap()
+    ));
+
+    testenv
+        .command()
+        .args(&["--os", "linux", "--list", "--group-by-platform"])
+        .assert()
+        .success()
+        .stdout(similar(
+            "## custom\nmytool\n\n## linux\napt\nip\n\n## common\nip\ntar\n",
+        ));
+    testenv
+        .command()
+        .args(&[
+            "--os",
+            "linux",
+            "--list",
+            "--group-by-platform",
+            "--custom-only",
+        ])
+        .assert()
+        .success()
+        .stdout(similar("## custom\nmytool\n"));
+    testenv
+        .command()
+        .args(&[
+            "--os",
+            "osx",
+            "--list",
+            "--group-by-platform",
+            "--format",
+            "json",
+        ])
+        .assert()
+        .success()
+        .stdout(similar(
+            r#"{"common":["ip","tar"],"custom":["mytool"],"osx":["brew"]}"#.to_owned() + "\n",
+        ));
+}
+
+#[test]
+fn test_list_sort() {
+    let testenv = TestEnv::new();
+
+    testenv.add_entry("tar", "");
+    testenv.add_entry("ip", "");
+    testenv.add_os_entry("linux", "ip", "");
+    testenv.add_os_entry("linux", "apt", "");
+    testenv.add_page_entry("mytool", "");
+    testenv.write_config(format!(
+        "[directories]\ncustom_pages_dir = '{}'",
+        testenv.custom_pages_dir.path().to_str().unwrap()
+    ));
+
+    testenv
+        .command()
+        .args(&["--os", "linux", "--list", "--sort", "name"])
+        .assert()
+        .success()
+        .stdout(similar("apt\nip\nmytool\ntar\n"));
+
+    // Pages are placed under the platform they are shown from
+    testenv
+        .command()
+        .args(&["--os", "linux", "--list", "--sort", "platform"])
+        .assert()
+        .success()
+        .stdout(similar("mytool\napt\nip\ntar\n"));
+
+    // The most recently changed page comes first
+    std::thread::sleep(Duration::from_millis(50));
+    testenv.add_entry("tar", "# tar\n");
+    testenv
+        .command()
+        .args(&["--os", "linux", "--list", "--sort", "mtime", "--limit", "1"])
+        .assert()
+        .success()
+        .stdout(similar("tar\n"));
+
+    testenv
+        .command()
+        .args(&["--list", "--sort", "size"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_dump_index() {
+    let testenv = TestEnv::new();
+
+    testenv.add_entry("tar", "");
+    testenv.add_os_entry("linux", "tar", "");
+    testenv.add_os_entry("osx", "brew", "");
+    let de_dir = testenv.cache_dir.path().join("tldr-master/pages.de/common");
+    create_dir_all(&de_dir).unwrap();
+    fs::write(de_dir.join("tar.md"), "").unwrap();
+    testenv.add_page_entry("mytool", "");
+    testenv.write_config(format!(
+        "[directories]\ncustom_pages_dir = '{}'",
+        testenv.custom_pages_dir.path().to_str().unwrap()
+    ));
+
+    testenv
+        .command()
+        .arg("--dump-index")
+        .assert()
+        .success()
+        .stdout(similar(
+            "brew\tosx\ten\nmytool\tcustom\t\ntar\tcommon,linux\tde,en\n",
+        ));
+    testenv
+        .command()
+        .args(&["--dump-index", "--format", "json"])
+        .assert()
+        .success()
+        .stdout(contains(
+            r#"{"name":"tar","platforms":["common","linux"],"languages":["de","en"]}"#,
+        ));
+}
+
+#[test]
+fn test_list_long() {
+    let testenv = TestEnv::new();
+
+    testenv.add_entry(
+        "tar",
+        "# tar\n\n> Archiving utility.\n> More information.\n",
+    );
+    testenv.add_entry(
+        "ls",
+        "# ls\n\n> \u{5217}\u{51fa}\u{76ee}\u{5f55}\u{5185}\u{5bb9}\n",
+    );
+    testenv.add_entry("empty", "# empty\n");
 
     testenv
         .command()
-        .args(&["--list"])
+        .args(&["--list", "--long"])
+        .env("COLUMNS", "80")
         .assert()
         .success()
-        .stdout("foo\n");
-
-    testenv.add_entry("bar", "");
-    testenv.add_entry("baz", "");
-    testenv.add_entry("qux", "");
+        .stdout(
+            "empty\n\
+             ls     \u{5217}\u{51fa}\u{76ee}\u{5f55}\u{5185}\u{5bb9}\n\
+             tar    Archiving utility.\n",
+        );
 
+    // Descriptions are truncated without cutting wide characters in half
     testenv
         .command()
-        .args(&["--list"])
+        .args(&["--list", "--long"])
+        .env("COLUMNS", "14")
         .assert()
         .success()
-        .stdout("bar\nbaz\nfoo\nqux\n");
+        .stdout(
+            "empty\n\
+             ls     \u{5217}\u{51fa}\u{76ee}\u{2026}\n\
+             tar    Archiv\u{2026}\n",
+        );
 }
 
 #[test]
@@ -604,6 +2944,500 @@ fn test_custom_patch_does_not_append_to_custom() {
         .stdout(similar(expected));
 }
 
+#[test]
+fn test_page_url() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("tar", "# tar\n\n> Archiving utility.");
+    testenv.add_os_entry("linux", "ip", "# ip\n\n> Show interfaces.");
+    testenv.add_entry("git-commit", "# git commit\n\n> Commit files.");
+
+    testenv
+        .command()
+        .args(&["--page-url", "tar"])
+        .assert()
+        .success()
+        .stdout(similar("https://tldr.inbrowser.app/pages/common/tar\n"));
+    testenv
+        .command()
+        .args(&["--os", "linux", "--page-url", "IP"])
+        .assert()
+        .success()
+        .stdout(similar("https://tldr.inbrowser.app/pages/linux/ip\n"));
+    testenv
+        .command()
+        .args(&["--page-url", "git commit"])
+        .assert()
+        .success()
+        .stdout(similar(
+            "https://tldr.inbrowser.app/pages/common/git-commit\n",
+        ));
+    testenv
+        .command()
+        .args(&["--page-url", "nonexistent"])
+        .assert()
+        .failure()
+        .code(1)
+        .stderr(contains("Page nonexistent not found in cache"));
+
+    testenv.write_config("[display]\npage_url_base = \"https://tldr.example.com/pages/\"\n");
+    testenv
+        .command()
+        .args(&["--page-url", "tar"])
+        .assert()
+        .success()
+        .stdout(similar("https://tldr.example.com/pages/common/tar\n"));
+}
+
+#[test]
+fn test_explain_without_man() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("tar", "# tar\n\n> Archiving utility.");
+    let empty_dir = tempfile::tempdir().unwrap();
+
+    // Without `man`, only the page is rendered
+    testenv
+        .command()
+        .args(&["--explain", "tar"])
+        .env("PATH", empty_dir.path())
+        .assert()
+        .success()
+        .stdout(similar("\n  Archiving utility.\n\n"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_explain() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let testenv = TestEnv::new();
+    testenv.add_entry("tar", "# tar\n\n> Archiving utility.");
+
+    // A `man` that prints a formatted page, with bold section headers
+    let bin_dir = tempfile::tempdir().unwrap();
+    let man = bin_dir.path().join("man");
+    fs::write(
+        &man,
+        "#!/bin/sh\n\
+         [ \"$1\" = tar ] || exit 16\n\
+         printf 'TAR(1)   User Commands   TAR(1)\\n\\n'\n\
+         printf 'N\\bNA\\bAM\\bME\\bE\\n       tar - an archiving utility\\n\\n'\n\
+         printf 'SYNOPSIS\\n       tar -c [-f ARCHIVE] [FILE...]\\n       tar -x [-f ARCHIVE]\\n\\n'\n\
+         printf 'DESCRIPTION\\n       Saves many files together.\\n'\n",
+    )
+    .unwrap();
+    fs::set_permissions(&man, fs::Permissions::from_mode(0o755)).unwrap();
+
+    testenv
+        .command()
+        .args(&["--explain", "tar"])
+        .env("PATH", bin_dir.path())
+        .assert()
+        .success()
+        .stdout(similar(
+            "  tar - an archiving utility\n  \
+             Usage: tar -c [-f ARCHIVE] [FILE...]\n  \
+             \x20      tar -x [-f ARCHIVE]\n\
+             \n  Archiving utility.\n\n",
+        ));
+
+    // Pages without a man page are rendered alone
+    testenv.add_entry("sl", "# sl\n\n> Steam locomotive.");
+    testenv
+        .command()
+        .args(&["--explain", "sl"])
+        .env("PATH", bin_dir.path())
+        .assert()
+        .success()
+        .stdout(similar("\n  Steam locomotive.\n\n"));
+}
+
+#[test]
+fn test_title_case() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("git-commit", "# Git Commit\n\n> Commit files.\n");
+
+    for (title_case, name) in &[
+        ("source", "Git Commit"),
+        ("lower", "git commit"),
+        ("command", "git commit"),
+    ] {
+        testenv.write_config(format!("[display]\ntitle_case = \"{}\"\n", title_case));
+        testenv
+            .command()
+            .args(&["--format", "json", "git", "commit"])
+            .assert()
+            .success()
+            .stdout(contains(format!("\"name\": \"{}\"", name)));
+    }
+
+    // The requested name is used as is
+    testenv
+        .command()
+        .args(&["--format", "json", "GIT-COMMIT"])
+        .assert()
+        .success()
+        .stdout(contains("\"name\": \"GIT-COMMIT\""));
+}
+
+#[test]
+fn test_line_numbers() {
+    let testenv = TestEnv::new();
+    let page = "# tar\n\n> Archiving utility.\n\n\
+                - Create an archive:\n\n`tar cf {{target.tar}} {{file}}`\n\n\
+                - List the contents:\n\n`tar tf {{source.tar}}`\n";
+    testenv.add_entry("tar", page);
+
+    testenv
+        .command()
+        .args(&["--color", "never", "--line-numbers", "tar"])
+        .assert()
+        .success()
+        .stdout(contains(" 1 tar cf target.tar file"))
+        .stdout(contains(" 2 tar tf source.tar"));
+
+    testenv.write_config("[display]\nline_numbering = \"example\"\n");
+    testenv
+        .command()
+        .args(&["--color", "never", "--line-numbers", "tar"])
+        .assert()
+        .success()
+        .stdout(contains(" 1 tar cf target.tar file"))
+        .stdout(contains(" 1 tar tf source.tar"));
+
+    // The raw markdown is not numbered
+    testenv
+        .command()
+        .args(&["--markdown", "--line-numbers", "tar"])
+        .assert()
+        .success()
+        .stdout(page);
+}
+
+#[test]
+fn test_color_depth_variants() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "tar",
+        "# tar\n\n> Archiving utility.\n\n- List:\n\n`tar tf`\n",
+    );
+    testenv.write_config(
+        "[style.example_code]\nforeground = 'cyan'\nforeground_256 = { ansi = 45 }\n\
+         foreground_truecolor = { rgb = { r = 0, g = 200, b = 255 } }\n",
+    );
+
+    for (colorterm, term, code) in &[
+        ("truecolor", "xterm-256color", "\x1b[38;2;0;200;255m"),
+        ("", "xterm-256color", "\x1b[38;5;45m"),
+        ("", "xterm", "\x1b[36m"),
+    ] {
+        testenv
+            .command()
+            .args(&["--color", "always", "tar"])
+            .env("COLORTERM", colorterm)
+            .env("TERM", term)
+            .assert()
+            .success()
+            .stdout(contains(format!("{} tf", code)));
+    }
+}
+
+#[test]
+fn test_description_markup() {
+    let testenv = TestEnv::new();
+    let page = "# tar\n\n> Archiving utility, see `man tar` for *all* options.\n";
+    testenv.add_entry("tar", page);
+
+    testenv
+        .command()
+        .args(&["--color", "always", "tar"])
+        .assert()
+        .success()
+        .stdout(contains("\x1b[36mman tar"))
+        .stdout(contains("\x1b[1mall"));
+    for flag in &["--no-styling", "--color=never"] {
+        testenv
+            .command()
+            .args(&[flag, "tar"])
+            .assert()
+            .success()
+            .stdout(contains("Archiving utility, see man tar for all options."));
+    }
+    testenv
+        .command()
+        .args(&["--markdown", "tar"])
+        .assert()
+        .success()
+        .stdout(similar(page));
+}
+
+#[test]
+fn test_json_format() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "tar",
+        "# tar\n\n> Archiving utility.\n> Often combined with gzip.\n\n\
+         - Create an archive:\n\n`tar cf {{target.tar}} {{file}}`\n",
+    );
+    testenv.add_patch_entry(
+        "tar",
+        "# tar\n\n- Custom example:\n\n<!-- language: bash -->\n`tar tf {{source.tar}}`\n",
+    );
+    testenv.write_config(format!(
+        "[directories]\ncustom_pages_dir = '{}'",
+        testenv.custom_pages_dir.path().to_str().unwrap()
+    ));
+
+    testenv
+        .command()
+        .args(&["--format", "json", "--color", "always", "tar"])
+        .assert()
+        .success()
+        .stdout(similar(
+            r#"{
+  "name": "tar",
+  "description": "Archiving utility.\nOften combined with gzip.",
+  "examples": [
+    {
+      "description": "Create an archive:",
+      "command": "tar cf {{target.tar}} {{file}}",
+      "language": "shell"
+    },
+    {
+      "description": "Custom example:",
+      "command": "tar tf {{source.tar}}",
+      "language": "bash"
+    }
+  ]
+}
+"#,
+        ));
+
+    // The hint is not rendered
+    testenv
+        .command()
+        .args(&["tar"])
+        .assert()
+        .success()
+        .stdout(contains("language").not());
+}
+
+#[test]
+fn test_see_also() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "netstat",
+        "# netstat\n\n> Show connections.\n> See also: `ss`, `lsof`.\n\n- List:\n\n`netstat`\n",
+    );
+    testenv.add_entry("ss", "# ss\n\n> Show sockets.\n");
+    testenv.write_config(
+        "[display]\nhyperlink_see_also = true\n[style.command_name]\nforeground = \"cyan\"\n",
+    );
+
+    testenv
+        .command()
+        .args(&["--color", "always", "netstat"])
+        .assert()
+        .success()
+        .stdout(contains(
+            "See also: \x1b[36m\x1b]8;;https://tldr.inbrowser.app/pages/common/ss\x1b\\ss\
+             \x1b]8;;\x1b\\\x1b[0m, \x1b[36mlsof\x1b[0m.",
+        ));
+    testenv
+        .command()
+        .args(&["--color", "never", "netstat"])
+        .assert()
+        .success()
+        .stdout(contains("  See also: ss, lsof.\n"));
+    testenv
+        .command()
+        .args(&["--format", "json", "netstat"])
+        .assert()
+        .success()
+        .stdout(contains("\"see_also\": [\n    \"ss\",\n    \"lsof\"\n  ]"));
+}
+
+#[test]
+fn test_synopsis() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "tar",
+        "# tar\n\n> Archiving utility.\n> Usage: `tar [options] [file ...]`\n\n- List:\n\n`tar tf {{file}}`\n",
+    );
+    testenv.write_config("[style.synopsis]\nbold = true\n");
+
+    testenv
+        .command()
+        .args(&["--color", "always", "tar"])
+        .assert()
+        .success()
+        .stdout(contains(
+            "  Usage: \x1b[1mtar [options] [file ...]\x1b[0m\n",
+        ));
+    testenv
+        .command()
+        .args(&["--color", "never", "tar"])
+        .assert()
+        .success()
+        .stdout(contains(
+            "  Archiving utility.\n  Usage: tar [options] [file ...]\n",
+        ));
+    testenv
+        .command()
+        .args(&["--format", "json", "tar"])
+        .assert()
+        .success()
+        .stdout(contains("\"synopsis\": \"tar [options] [file ...]\""));
+
+    // Pages without a synopsis have no synopsis in the JSON output
+    testenv.add_entry("ls", "# ls\n\n> List files.\n");
+    testenv
+        .command()
+        .args(&["--format", "json", "ls"])
+        .assert()
+        .success()
+        .stdout(contains("synopsis").not());
+}
+
+#[test]
+fn test_json_errors() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("git", "# git\n\n> Version control.");
+
+    testenv
+        .command()
+        .args(&["--format", "json", "--quiet", "gti"])
+        .assert()
+        .code(1)
+        .stdout(similar("{\"error\":\"not_found\",\"command\":\"gti\"}\n"))
+        .stderr(is_empty());
+    testenv
+        .command()
+        .args(&["gti"])
+        .assert()
+        .code(1)
+        .stdout(is_empty());
+
+    fs::remove_dir_all(testenv.cache_dir.path().join("tldr-master")).unwrap();
+    testenv
+        .command()
+        .args(&["--format", "json", "git"])
+        .assert()
+        .code(3)
+        .stdout(similar("{\"error\":\"cache_missing\"}\n"))
+        .stderr(contains("Cache not found"));
+}
+
+#[test]
+fn test_which() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("tar", "# tar\n\n> Archiving utility.");
+    testenv.add_entry("ip", "# ip\n\n> Show interfaces.");
+    testenv.add_os_entry("linux", "ip", "# ip\n\n> Show interfaces.");
+    testenv.add_entry("git", "# git\n\n> Version control.");
+    testenv.add_page_entry("pwd", "# pwd\n\n> Print working directory.\n");
+    testenv.add_patch_entry("tar", "- Custom example:\n\n`tar`\n");
+    testenv.write_config(format!(
+        "[directories]\ncustom_pages_dir = '{}'",
+        testenv.custom_pages_dir.path().to_str().unwrap()
+    ));
+
+    let pages_dir = fs::canonicalize(testenv.cache_dir.path())
+        .unwrap()
+        .join("tldr-master")
+        .join("pages");
+    let custom_pages_dir = fs::canonicalize(testenv.custom_pages_dir.path()).unwrap();
+
+    testenv
+        .command()
+        .args(&["--os", "linux", "--which", "ip"])
+        .assert()
+        .success()
+        .stdout(similar(format!(
+            "{}\n",
+            pages_dir.join("linux/ip.md").display()
+        )));
+    testenv
+        .command()
+        .args(&["--os", "osx", "--which", "ip"])
+        .assert()
+        .success()
+        .stdout(similar(format!(
+            "{}\n",
+            pages_dir.join("common/ip.md").display()
+        )));
+    testenv
+        .command()
+        .args(&["--which", "pwd"])
+        .assert()
+        .success()
+        .stdout(similar(format!(
+            "{}\n",
+            custom_pages_dir.join("pwd.page").display()
+        )));
+
+    // The patch of a page is printed after the page
+    testenv
+        .command()
+        .args(&["--which", "tar"])
+        .assert()
+        .success()
+        .stdout(similar(format!(
+            "{}\n{}\n",
+            pages_dir.join("common/tar.md").display(),
+            custom_pages_dir.join("tar.patch").display()
+        )));
+
+    // Subcommands fall back to the page of the command
+    testenv
+        .command()
+        .args(&["--which", "git commit"])
+        .assert()
+        .success()
+        .stdout(similar(format!(
+            "{}\n",
+            pages_dir.join("common/git.md").display()
+        )));
+    testenv
+        .command()
+        .args(&["--which", "nonexistent"])
+        .assert()
+        .code(1)
+        .stdout(is_empty())
+        .stderr(contains("Page nonexistent not found"));
+}
+
+/// End-End test to ensure that --diff compares custom pages with upstream pages
+#[test]
+fn test_custom_page_diff() {
+    let testenv = TestEnv::new();
+
+    // set custom pages directory
+    testenv.write_config(format!(
+        "[directories]\ncustom_pages_dir = '{}'",
+        testenv.custom_pages_dir.path().to_str().unwrap()
+    ));
+
+    testenv.add_entry("foo", "# foo\n\n> Upstream description.\n");
+
+    testenv
+        .command()
+        .args(&["--diff", "foo"])
+        .assert()
+        .failure()
+        .stderr(contains("Only an upstream page exists for foo"));
+
+    testenv.add_page_entry("foo", "# foo\n\n> Custom description.\n");
+
+    testenv
+        .command()
+        .args(&["--diff", "foo"])
+        .assert()
+        .success()
+        .stdout(contains(
+            "@@ -1,3 +1,3 @@\n # foo\n \n-> Upstream description.\n+> Custom description.\n",
+        ));
+}
+
 #[test]
 #[cfg(target_os = "windows")]
 fn test_pager_warning() {